@@ -0,0 +1,372 @@
+//! Environment diagnostics (`agent-skills doctor`)
+
+use super::{
+    audit_file, check_skill_spec, config_file, discover_skills, extension_interpreter, is_executable, load_policy,
+    plugin_dir, policy_file, policy_violation, say, script_interpreter, secrets_file, skill_files, skills_dir,
+    source_cache_dir, state_dir, state_file, stats_file, toggles_file, trust_file, visible_subdirs, ActionError,
+    ErrorKind, Skill,
+};
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Programs skills commonly run scripts with
+const INTERPRETERS: &[&str] = &["python3", "node", "bash", "uv"];
+
+/// Findings of a `doctor` run; each is also printed as it is made
+#[derive(Default)]
+struct DoctorReport {
+    problems: usize,
+    warnings: usize,
+    /// Warnings and problems, for `--json`
+    findings: Vec<serde_json::Value>,
+}
+
+impl DoctorReport {
+    fn ok(&self, message: impl std::fmt::Display) {
+        say(format!("  ok    {}", message));
+    }
+
+    fn warn(&mut self, message: impl std::fmt::Display, fix: impl std::fmt::Display) {
+        self.warnings += 1;
+        self.record("warning", message, fix);
+    }
+
+    fn fail(&mut self, message: impl std::fmt::Display, fix: impl std::fmt::Display) {
+        self.problems += 1;
+        self.record("problem", message, fix);
+    }
+
+    fn record(&mut self, level: &str, message: impl std::fmt::Display, fix: impl std::fmt::Display) {
+        let label = if level == "problem" { "FAIL" } else { "WARN" };
+        say(format!("  {}  {}\n        fix: {}", label, message, fix));
+        self.findings.push(serde_json::json!({
+            "level": level,
+            "message": message.to_string(),
+            "fix": fix.to_string(),
+        }));
+    }
+}
+
+/// Check the environment skills depend on and print a fix-it report: `doctor`
+///
+/// Fails if any problems (warnings aside) were found.
+pub fn handle_doctor_cli() -> Result<serde_json::Value, ActionError> {
+    let mut report = DoctorReport::default();
+    let skills = discover_skills();
+
+    say("Tools");
+    match Command::new("git").arg("--version").output() {
+        Ok(output) if output.status.success() => report.ok(String::from_utf8_lossy(&output.stdout).trim()),
+        _ => report.fail("git is not available", "install git; it is needed to install and mirror skills"),
+    }
+    let needs = interpreter_needs(&skills);
+    for interpreter in INTERPRETERS {
+        let users: Vec<&String> = needs.iter().filter(|(i, _)| i == interpreter).map(|(_, u)| u).collect();
+        match (find_in_path(interpreter), users.is_empty()) {
+            (Some(path), _) => report.ok(format!("{} ({})", interpreter, path.display())),
+            (None, true) => say(format!("  --    {} not found (no installed skill needs it)", interpreter)),
+            (None, false) => report.fail(
+                format!("{} not found, needed by {}", interpreter, join_display(&users)),
+                format!("install {} or put it on PATH", interpreter),
+            ),
+        }
+    }
+    // Absolute shebang interpreters, e.g. `#!/usr/local/bin/ruby`
+    for (interpreter, user) in needs.iter().filter(|(i, _)| i.starts_with('/')) {
+        if !Path::new(interpreter).exists() {
+            report.fail(
+                format!("{} not found, needed by {}", interpreter, user),
+                "install it, or change the script's #! line",
+            );
+        }
+    }
+
+    say("\nSkills");
+    check_skill_dirs(&mut report, &skills);
+
+    say("\nLeftovers");
+    check_stale_dirs(&mut report);
+
+    say("\nState files");
+    check_state_files(&mut report);
+
+    say("\nScripts");
+    check_script_permissions(&mut report, &skills);
+
+    say("");
+    match (report.problems, report.warnings) {
+        (0, 0) => say("No problems found."),
+        (problems, warnings) => say(format!("{} problem(s), {} warning(s).", problems, warnings)),
+    }
+    if report.problems > 0 {
+        let problems: Vec<String> = report
+            .findings
+            .iter()
+            .filter(|f| f["level"] == "problem")
+            .filter_map(|f| f["message"].as_str().map(String::from))
+            .collect();
+        return Err(ActionError::new(
+            ErrorKind::Failed,
+            format!("The environment has problems: {}", problems.join("; ")),
+        ));
+    }
+    Ok(serde_json::json!({ "warnings": report.warnings, "findings": report.findings }))
+}
+
+fn join_display(items: &[&String]) -> String {
+    items.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ")
+}
+
+/// Look a program up on PATH
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    let path = env::var_os("PATH")?;
+    env::split_paths(&path)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file() && is_executable(candidate))
+}
+
+/// Program named by a script's `#!` line (`env` and `env -S` are looked through)
+fn shebang_program(script: &Path) -> Option<String> {
+    let mut first_line = String::new();
+    io::BufReader::new(fs::File::open(script).ok()?)
+        .read_line(&mut first_line)
+        .ok()?;
+    let mut words = first_line.strip_prefix("#!")?.split_whitespace();
+    let program = words.next()?;
+    if program.rsplit('/').next() != Some("env") {
+        return Some(program.to_string());
+    }
+    words.find(|w| !w.starts_with('-')).map(String::from)
+}
+
+/// Whether a file looks like something execute_script would run
+fn is_script_file(path: &Path) -> bool {
+    extension_interpreter(path).is_some() || shebang_program(path).is_some()
+}
+
+/// Files of a skill that may be run as scripts: everything under `scripts/` and `tests/`,
+/// plus any tool or hook script kept elsewhere. Assets and references are left alone.
+fn skill_scripts(skill: &Skill) -> Vec<PathBuf> {
+    let mut scripts = skill_files(&skill.dir.join("scripts"), Path::new("scripts"));
+    scripts.extend(skill_files(&skill.dir.join("tests"), Path::new("tests")));
+    let declared: BTreeSet<&String> = skill.tools.iter().map(|t| &t.script).chain(skill.hooks.values()).collect();
+    for script in declared {
+        let rel = PathBuf::from(script);
+        if skill.dir.join(&rel).is_file() && !scripts.contains(&rel) {
+            scripts.push(rel);
+        }
+    }
+    scripts
+}
+
+/// (interpreter, "skill (file)") for every script of the given skills, as execute_script would run it
+fn interpreter_needs(skills: &[Skill]) -> Vec<(String, String)> {
+    let mut needs = Vec::new();
+    for skill in skills {
+        for rel in skill_scripts(skill) {
+            let path = skill.dir.join(&rel);
+            let interpreter = if is_executable(&path) {
+                shebang_program(&path)
+            } else {
+                script_interpreter(&path).map(String::from)
+            };
+            if let Some(interpreter) = interpreter {
+                needs.push((interpreter, format!("{} ({})", skill.id(), rel.display())));
+            }
+        }
+    }
+    needs
+}
+
+/// Every directory under the skills root should parse as a skill or be a namespace of them
+fn check_skill_dirs(report: &mut DoctorReport, skills: &[Skill]) {
+    let root = skills_dir();
+    let policy = load_policy();
+    let mut dirs = Vec::new();
+    for path in visible_subdirs(&root) {
+        if path.join("SKILL.md").exists() {
+            dirs.push(path);
+        } else {
+            let children = visible_subdirs(&path);
+            if children.is_empty() {
+                report.warn(
+                    format!("{} contains no skills", path.display()),
+                    format!("remove it: rm -r {}", path.display()),
+                );
+            }
+            dirs.extend(children);
+        }
+    }
+
+    // Broken symlinks (e.g. an imported skill whose source moved) are skipped by discovery
+    let entries = fs::read_dir(&root)
+        .map(|e| e.flatten().map(|e| e.path()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    for link in entries.iter().filter(|p| p.is_symlink() && !p.exists()) {
+        report.fail(
+            format!("{} is a broken symlink", link.display()),
+            format!("re-import the skill or remove the link: rm {}", link.display()),
+        );
+    }
+
+    for dir in dirs {
+        let Some(skill) = skills.iter().find(|s| s.dir == dir) else {
+            let errors = check_skill_spec(&dir).errors;
+            let reason = if errors.is_empty() {
+                "SKILL.md could not be parsed".to_string()
+            } else {
+                errors.join("; ")
+            };
+            report.fail(
+                format!("{} is not loaded: {}", dir.display(), reason),
+                "fix SKILL.md, or remove the directory",
+            );
+            continue;
+        };
+        match policy.as_ref().map(|p| policy_violation(p, skill)) {
+            Ok(Some(reason)) => report.warn(
+                format!("{} is hidden by policy: {}", skill.id(), reason),
+                format!("remove it (agent-skills remove {}) or ask your admin", skill.id()),
+            ),
+            Ok(None) => report.ok(skill.id()),
+            Err(_) => {}
+        }
+    }
+    if let Err(e) = policy {
+        report.fail(e, format!("fix or remove {}", policy_file().display()));
+    }
+}
+
+/// Whether the process that last took the install lock is still running
+///
+/// Installs write their pid into the lock file. Only the pid is read: taking
+/// the lock here would make an install started meanwhile fail.
+fn install_running() -> bool {
+    let pid = fs::read_to_string(plugin_dir().join(".install.lock"))
+        .ok()
+        .and_then(|pid| pid.trim().parse::<u32>().ok());
+    let Some(pid) = pid else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .stderr(std::process::Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = pid;
+        true
+    }
+}
+
+/// Staging directories left behind by interrupted installs or clones
+fn check_stale_dirs(report: &mut DoctorReport) {
+    let installing = install_running();
+
+    let mut stale = Vec::new();
+    for dir in [skills_dir(), source_cache_dir()] {
+        let entries = fs::read_dir(&dir)
+            .map(|e| e.flatten().map(|e| e.path()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        stale.extend(entries.into_iter().filter(|p| {
+            let name = p.file_name().unwrap_or_default().to_string_lossy();
+            name.starts_with(".tmp_") || (name == ".install_txn" && !installing)
+        }));
+    }
+    if stale.is_empty() {
+        report.ok("no leftover staging directories");
+    }
+    for dir in stale {
+        report.warn(
+            format!("{} is left over from an interrupted install", dir.display()),
+            format!("rm -rf {}", dir.display()),
+        );
+    }
+}
+
+/// Every JSON state file that exists must be readable and parse
+fn check_state_files(report: &mut DoctorReport) {
+    let mut files = vec![
+        config_file(),
+        secrets_file(),
+        policy_file(),
+        trust_file(),
+        toggles_file(),
+        stats_file(),
+        state_file(),
+    ];
+    files.extend(
+        fs::read_dir(state_dir())
+            .map(|e| e.flatten().map(|e| e.path()).collect::<Vec<_>>())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|p| p.extension().is_some_and(|e| e == "json")),
+    );
+
+    let mut checked = 0;
+    for file in files.iter().filter(|f| f.exists()) {
+        checked += 1;
+        match fs::read_to_string(file) {
+            Err(e) => report.fail(
+                format!("cannot read {}: {}", file.display(), e),
+                format!("check its permissions: chmod u+rw {}", file.display()),
+            ),
+            Ok(content) => {
+                if let Err(e) = serde_json::from_str::<serde_json::Value>(&content) {
+                    report.fail(
+                        format!("{} is not valid JSON: {}", file.display(), e),
+                        format!("fix or remove {}", file.display()),
+                    );
+                }
+            }
+        }
+    }
+    let audit = audit_file();
+    if audit.exists() {
+        checked += 1;
+        if let Err(e) = fs::OpenOptions::new().append(true).open(&audit) {
+            report.fail(
+                format!("cannot write {}: {}", audit.display(), e),
+                format!("check its permissions: chmod u+rw {}", audit.display()),
+            );
+        }
+    }
+    report.ok(format!("{} state file(s) checked", checked));
+}
+
+/// Scripts that can't be run the way execute_script runs them
+fn check_script_permissions(report: &mut DoctorReport, skills: &[Skill]) {
+    let mut scripts = 0;
+    for skill in skills {
+        for rel in skill_scripts(skill) {
+            let path = skill.dir.join(&rel);
+            if !is_script_file(&path) {
+                continue;
+            }
+            scripts += 1;
+            let by_extension = extension_interpreter(&path).is_some();
+            if is_executable(&path) {
+                if shebang_program(&path).is_none() && by_extension {
+                    report.warn(
+                        format!("{} ({}) is executable but has no #! line", skill.id(), rel.display()),
+                        format!("add a #! line, or chmod -x {} to run it by extension", path.display()),
+                    );
+                }
+            } else if !by_extension {
+                report.fail(
+                    format!("{} ({}) has a #! line but isn't executable", skill.id(), rel.display()),
+                    format!("chmod +x {}", path.display()),
+                );
+            }
+        }
+    }
+    report.ok(format!("{} script(s) checked", scripts));
+}
@@ -0,0 +1,515 @@
+//! chibi hook handlers, and the fan-out to hooks declared by skills
+
+use super::{
+    args_hash, audit, clear_active_skill, enabled_skills, execute_script, get_active_skill, is_tool_allowed,
+    list_skills, load_config, load_stats, lookup_tool, record_usage, resolve_skill, set_active_skill,
+    skill_instructions, tool_table, AuditEntry, CHIBI_HOOKS, FORKED_SKILL_ENV, OutputPolicy, Skill, SkillTriggers,
+    ToolEntry, ToolKind,
+};
+use serde::{Deserialize, Serialize};
+use std::env;
+
+/// Hook data for pre_tool
+#[derive(Deserialize, Default)]
+struct PreToolHookData {
+    tool_name: Option<String>,
+    arguments: Option<serde_json::Value>,
+}
+
+/// Hook data for post_tool
+#[derive(Deserialize, Default)]
+struct PostToolHookData {
+    tool_name: Option<String>,
+    result: Option<String>,
+}
+
+/// Hook response replacing a tool's result
+#[derive(Serialize)]
+struct ResultResponse {
+    result: String,
+}
+
+/// Hook data for pre_message
+#[derive(Deserialize, Default)]
+struct PreMessageHookData {
+    prompt: Option<String>,
+}
+
+/// Hook response for blocking a tool
+#[derive(Serialize)]
+struct BlockResponse {
+    block: bool,
+    message: String,
+}
+
+/// Hook response for injecting content
+#[derive(Serialize)]
+struct InjectResponse {
+    inject: String,
+}
+
+/// Response fields a later hook can see and replace: skills' hooks receive the
+/// payload with the value so far, and the last value given wins
+const CHAINED_HOOK_FIELDS: &[&str] = &["prompt", "result"];
+
+/// Run enabled skills' scripts for `hook`, in skill id order, after agent-skills' own response
+///
+/// Each script gets the hook payload on stdin (and `CHIBI_HOOK` in its
+/// environment); a JSON object on stdout is its response. Chained fields from
+/// earlier responses are written into the payload first. A script that fails
+/// or isn't approved is skipped and reported on stderr (the run is audited).
+pub fn run_skill_hooks(hook: &str, payload: &str, own: serde_json::Value) -> Vec<serde_json::Value> {
+    // Most hooks agent-skills handles itself have no skill scripts; don't build the tool table for them
+    let declared = CHIBI_HOOKS.contains(&hook) && list_skills().iter().any(|s| s.hooks.contains_key(hook));
+    if !declared {
+        return vec![own];
+    }
+
+    let mut payload_value: Option<serde_json::Value> = serde_json::from_str(payload).ok();
+    let chain = |payload_value: &mut Option<serde_json::Value>, response: &serde_json::Value| {
+        if let (Some(serde_json::Value::Object(fields)), serde_json::Value::Object(given)) = (payload_value, response) {
+            for field in CHAINED_HOOK_FIELDS {
+                if let Some(value) = given.get(*field) {
+                    fields.insert(field.to_string(), value.clone());
+                }
+            }
+        }
+    };
+    chain(&mut payload_value, &own);
+    let mut responses = vec![own];
+
+    for skill in enabled_skills() {
+        let Some(script) = skill.hooks.get(hook) else {
+            continue;
+        };
+        let stdin = payload_value.as_ref().map_or(payload.to_string(), |v| v.to_string());
+        let failure = match execute_script(&skill, script, Vec::new(), Some(stdin)) {
+            Ok(output) if output.success => {
+                if let Ok(response @ serde_json::Value::Object(_)) = serde_json::from_str(output.stdout.trim()) {
+                    chain(&mut payload_value, &response);
+                    responses.push(response);
+                }
+                continue;
+            }
+            Ok(output) => output.display(),
+            Err(e) => e,
+        };
+        eprintln!("[agent-skills] {} hook of skill '{}' failed: {}", hook, skill.id(), failure);
+    }
+    responses
+}
+
+/// Merge hook responses in order: agent-skills' own first, then skills by id
+///
+/// Any `block` blocks, with the blocking messages joined; `inject` texts are
+/// concatenated; chained fields (`prompt`, `result`) keep the last value,
+/// since each hook saw the one before; any other field keeps the first value given.
+pub fn merge_hook_responses(responses: Vec<serde_json::Value>) -> serde_json::Value {
+    let mut merged = serde_json::Map::new();
+    let mut injects = Vec::new();
+    let mut block_messages = Vec::new();
+    let mut blocked = false;
+
+    for response in responses {
+        let serde_json::Value::Object(fields) = response else {
+            continue;
+        };
+        let blocks = fields.get("block").and_then(|b| b.as_bool()).unwrap_or(false);
+        for (key, value) in fields {
+            match key.as_str() {
+                "inject" => injects.extend(value.as_str().map(String::from)),
+                "block" => {}
+                "message" => {
+                    if blocks {
+                        block_messages.extend(value.as_str().map(String::from));
+                    }
+                }
+                k if CHAINED_HOOK_FIELDS.contains(&k) => {
+                    merged.insert(key, value);
+                }
+                _ => {
+                    merged.entry(key).or_insert(value);
+                }
+            }
+        }
+        blocked |= blocks;
+    }
+
+    if !injects.is_empty() {
+        merged.insert("inject".to_string(), serde_json::json!(injects.join("\n\n")));
+    }
+    if blocked {
+        merged.insert("block".to_string(), serde_json::json!(true));
+        merged.insert("message".to_string(), serde_json::json!(block_messages.join("\n")));
+    }
+    serde_json::Value::Object(merged)
+}
+
+pub fn handle_on_start_hook() -> serde_json::Value {
+    // The active skill state belongs to the calling context, not a forked sub-agent
+    if env::var(FORKED_SKILL_ENV).is_err() {
+        clear_active_skill();
+    }
+    serde_json::json!({})
+}
+
+pub fn handle_post_system_prompt_hook() -> serde_json::Value {
+    let skill_entries: Vec<(String, Skill)> = tool_table()
+        .into_iter()
+        .filter(|t| t.enabled)
+        .filter_map(|t| match t.kind {
+            ToolKind::Skill(skill) => Some((t.name, skill)),
+            _ => None,
+        })
+        .collect();
+    if skill_entries.is_empty() {
+        return serde_json::json!({});
+    }
+
+    // A skill a trigger auto-activated never had its instructions returned by a tool call
+    let auto_activated = get_active_skill()
+        .filter(|active| active.auto_activated)
+        .and_then(|active| skill_entries.iter().find(|(_, s)| s.id() == active.name))
+        .map(|(_, skill)| skill_instructions(skill));
+
+    let mut skill_entries = skill_entries;
+    if load_config().order_by_usage {
+        let stats = load_stats();
+        let last_used = |skill: &Skill| stats.get(&skill.id()).and_then(|s| s.last_used);
+        skill_entries.sort_by_key(|(_, skill)| std::cmp::Reverse(last_used(skill)));
+    }
+
+    let mut lines = vec!["## Available Agent Skills".to_string(), String::new()];
+    for (tool_name, skill) in skill_entries {
+        lines.push(format!("- **{}** (`{}`): {}", skill.id(), tool_name, skill.description));
+    }
+    lines.push(String::new());
+    lines.push("Use a skill's tool to invoke it and receive detailed instructions.".to_string());
+    lines.push("Tools named `<skill tool>__<name>` run a skill's scripts directly with typed arguments.".to_string());
+    if let Some(instructions) = auto_activated {
+        lines.push(String::new());
+        lines.push("## Active Skill".to_string());
+        lines.push(String::new());
+        lines.push("This skill was activated for the user's message; follow its instructions.".to_string());
+        lines.push(String::new());
+        lines.push(instructions);
+    }
+
+    let response = InjectResponse {
+        inject: lines.join("\n"),
+    };
+    serde_json::to_value(&response).unwrap()
+}
+
+/// Suggest (or auto-activate) skills whose triggers match the user's message
+///
+/// The message itself is left alone; suggestions are injected, and an
+/// auto-activated skill's instructions go in through post_system_prompt.
+pub fn handle_pre_message_hook(stdin_data: &str) -> serde_json::Value {
+    let hook_data: PreMessageHookData = serde_json::from_str(stdin_data).unwrap_or_default();
+    let Some(prompt) = hook_data.prompt.filter(|p| !p.trim().is_empty()) else {
+        return serde_json::json!({});
+    };
+
+    let mut nudges = Vec::new();
+    let mut activated = None;
+    for entry in tool_table().into_iter().filter(|t| t.enabled) {
+        let ToolKind::Skill(skill) = entry.kind else {
+            continue;
+        };
+        let Some(matched) = match_triggers(&skill.triggers, &prompt) else {
+            continue;
+        };
+        let id = skill.id();
+
+        // Only one skill can be active; forked skills run elsewhere, so they're only suggested
+        let activate = skill.triggers.auto_activate && !skill.fork_context && activated.is_none();
+        audit(AuditEntry {
+            tool: Some(entry.name.clone()),
+            trigger: Some(matched.clone()),
+            ..AuditEntry::new(&id, "trigger")
+        });
+
+        if activate {
+            set_active_skill(&id, skill.allowed_tools.clone(), true);
+            record_usage(&id, |s| s.invocations += 1);
+            audit(AuditEntry {
+                tool: Some(entry.name.clone()),
+                ..AuditEntry::new(&id, "activate")
+            });
+            activated = Some(skill);
+        } else {
+            nudges.push(format!(
+                "- Skill `{}` looks relevant (matched {}). Call `{}` to load its instructions.",
+                id, matched, entry.name
+            ));
+        }
+    }
+
+    if nudges.is_empty() && activated.is_none() {
+        return serde_json::json!({});
+    }
+
+    let mut notes = Vec::new();
+    if let Some(skill) = activated {
+        notes.push(format!(
+            "- Skill `{}` was activated for this message; its instructions are in the system prompt.",
+            skill.id()
+        ));
+    }
+    notes.extend(nudges);
+
+    let response = InjectResponse {
+        inject: format!("[agent-skills]\n{}", notes.join("\n")),
+    };
+    serde_json::to_value(&response).unwrap()
+}
+
+/// The first trigger that matches `message`, as `kind:value` (e.g. `keyword:pdf`)
+fn match_triggers(triggers: &SkillTriggers, message: &str) -> Option<String> {
+    for keyword in &triggers.keywords {
+        let pattern = format!(r"(?i)\b{}\b", regex::escape(keyword));
+        if regex::Regex::new(&pattern).is_ok_and(|re| re.is_match(message)) {
+            return Some(format!("keyword:{}", keyword));
+        }
+    }
+    for pattern in &triggers.patterns {
+        if regex::Regex::new(pattern).is_ok_and(|re| re.is_match(message)) {
+            return Some(format!("pattern:{}", pattern));
+        }
+    }
+
+    let globs: Vec<glob::Pattern> = triggers.files.iter().filter_map(|g| glob::Pattern::new(g).ok()).collect();
+    if globs.is_empty() {
+        return None;
+    }
+    let mentioned = message
+        .split_whitespace()
+        .map(|w| w.trim_matches(|c: char| "\"'`()[]{}<>,;:!?".contains(c)))
+        .filter(|w| w.contains('.') || w.contains('/'));
+    for word in mentioned {
+        let file_name = word.rsplit('/').next().unwrap_or(word);
+        if let Some(glob) = globs.iter().find(|g| g.matches(word) || g.matches(file_name)) {
+            return Some(format!("file:{}", glob.as_str()));
+        }
+    }
+    None
+}
+
+pub fn handle_pre_tool_hook(stdin_data: &str) -> serde_json::Value {
+    let hook_data: PreToolHookData = serde_json::from_str(stdin_data).unwrap_or_default();
+
+    let tool_name = hook_data.tool_name.unwrap_or_default();
+    let arguments = hook_data.arguments.unwrap_or_default();
+
+    let entry = lookup_tool(&tool_name);
+
+    // Track skill activation
+    if let Some(ToolEntry {
+        kind: ToolKind::Skill(skill),
+        enabled: true,
+        ..
+    }) = &entry
+    {
+        let id = skill.id();
+        // Forked skills run elsewhere, so they don't restrict the caller's tools
+        if !skill.fork_context {
+            set_active_skill(&id, skill.allowed_tools.clone(), false);
+        }
+        audit(AuditEntry {
+            tool: Some(tool_name.clone()),
+            args_hash: Some(args_hash(&arguments)),
+            ..AuditEntry::new(&id, "activate")
+        });
+        return serde_json::json!({});
+    }
+
+    // Enforce allowed-tools for active skill. agent-skills' own tools are always
+    // allowed, except copy_skill_asset: it writes to the workspace, so the
+    // skill's allowlist has to include it.
+    let exempt = entry.is_some() && tool_name != "copy_skill_asset";
+    if let Some(active) = get_active_skill().filter(|_| !exempt) {
+        if let Some(allowed) = &active.allowed_tools {
+            if !is_tool_allowed(&tool_name, allowed) {
+                audit(AuditEntry {
+                    tool: Some(tool_name.clone()),
+                    args_hash: Some(args_hash(&arguments)),
+                    ..AuditEntry::new(&active.name, "block")
+                });
+                let response = BlockResponse {
+                    block: true,
+                    message: format!(
+                        "Tool '{}' is not allowed while skill '{}' is active. Allowed tools: {}",
+                        tool_name, active.name, allowed
+                    ),
+                };
+                return serde_json::to_value(&response).unwrap();
+            }
+        }
+    }
+
+    serde_json::json!({})
+}
+
+/// Apply the active skill's output policy to a tool result
+pub fn handle_post_tool_hook(stdin_data: &str) -> serde_json::Value {
+    let hook_data: PostToolHookData = serde_json::from_str(stdin_data).unwrap_or_default();
+    let tool_name = hook_data.tool_name.unwrap_or_default();
+    let (Some(result), Some(active)) = (hook_data.result, get_active_skill()) else {
+        return serde_json::json!({});
+    };
+    let Ok(skill) = resolve_skill(&active.name) else {
+        return serde_json::json!({});
+    };
+    let policy = &skill.output_policy;
+    // agent-skills' own tools (skill instructions, sections, files, state) are
+    // only shaped when the policy names them
+    let listed = policy.tools.contains(&tool_name);
+    if !listed && (!policy.tools.is_empty() || lookup_tool(&tool_name).is_some()) {
+        return serde_json::json!({});
+    }
+
+    let (shaped, changes) = apply_output_policy(policy, &skill.id(), &result);
+    if changes.is_empty() {
+        return serde_json::json!({});
+    }
+    audit(AuditEntry {
+        tool: Some(tool_name),
+        detail: Some(changes.join(", ")),
+        ..AuditEntry::new(&skill.id(), "output")
+    });
+    serde_json::to_value(ResultResponse { result: shaped }).unwrap()
+}
+
+/// Redact, truncate and append reminders; returns the result and what changed
+fn apply_output_policy(policy: &OutputPolicy, skill_id: &str, result: &str) -> (String, Vec<String>) {
+    let mut output = result.to_string();
+    let mut changes = Vec::new();
+
+    let mut redactions = 0;
+    for pattern in &policy.redact {
+        let Ok(re) = regex::Regex::new(pattern) else {
+            continue;
+        };
+        redactions += re.find_iter(&output).count();
+        output = re.replace_all(&output, "[REDACTED]").into_owned();
+    }
+    if redactions > 0 {
+        changes.push(format!("redacted {}", redactions));
+    }
+
+    if let Some(max) = policy.max_length {
+        let length = output.chars().count();
+        if length > max {
+            output = output.chars().take(max).collect();
+            output.push_str(&format!(
+                "\n[... {} characters truncated by skill '{}' output policy]",
+                length - max,
+                skill_id
+            ));
+            changes.push(format!("truncated {} to {}", length, max));
+        }
+    }
+
+    if !policy.reminders.is_empty() {
+        for reminder in &policy.reminders {
+            output.push_str(&format!("\n\n[Reminder from skill '{}'] {}", skill_id, reminder));
+        }
+        changes.push(format!("reminders {}", policy.reminders.len()));
+    }
+
+    (output, changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn merged_hook_responses_follow_response_order() {
+        let responses = vec![
+            json!({ "inject": "own", "prompt": "p0", "model": "first" }),
+            json!({ "inject": "a", "prompt": "p1", "model": "second" }),
+            json!({ "block": true, "message": "no", "inject": "b" }),
+            json!({ "message": "ignored without block", "prompt": "p2" }),
+            json!("not an object"),
+        ];
+        let merged = merge_hook_responses(responses.clone());
+        assert_eq!(
+            merged,
+            json!({ "inject": "own\n\na\n\nb", "prompt": "p2", "model": "first", "block": true, "message": "no" })
+        );
+        // Same responses, same result: nothing depends on map or hash order
+        assert_eq!(merge_hook_responses(responses), merged);
+    }
+
+    #[test]
+    fn merged_hook_responses_join_block_messages() {
+        let merged = merge_hook_responses(vec![
+            json!({ "block": true, "message": "first" }),
+            json!({}),
+            json!({ "block": true, "message": "second" }),
+        ]);
+        assert_eq!(merged, json!({ "block": true, "message": "first\nsecond" }));
+        assert_eq!(merge_hook_responses(vec![json!({})]), json!({}));
+    }
+
+    #[test]
+    fn triggers_match_keywords_patterns_and_mentioned_files() {
+        let triggers = SkillTriggers {
+            keywords: vec!["pdf".to_string()],
+            patterns: vec![r"\bpages? \d+".to_string()],
+            files: vec!["*.xlsx".to_string(), "docs/*.md".to_string()],
+            ..Default::default()
+        };
+        let matched = |message: &str| match_triggers(&triggers, message);
+
+        assert_eq!(matched("Merge these PDF files").as_deref(), Some("keyword:pdf"));
+        assert_eq!(matched("print page 4").as_deref(), Some(r"pattern:\bpages? \d+"));
+        assert_eq!(matched("open (reports/q3.xlsx), please").as_deref(), Some("file:*.xlsx"));
+        assert_eq!(matched("see docs/intro.md").as_deref(), Some("file:docs/*.md"));
+
+        assert_eq!(matched("a pdfkit question"), None);
+        assert_eq!(matched("read intro.md"), None);
+        assert_eq!(match_triggers(&SkillTriggers::default(), "pdf"), None);
+    }
+
+    #[test]
+    fn output_policy_redacts_every_match() {
+        let policy = OutputPolicy {
+            redact: vec!["sk-[a-z0-9]+".to_string()],
+            ..Default::default()
+        };
+        let (output, changes) = apply_output_policy(&policy, "acme/keys", "a sk-abc1 b sk-def2");
+        assert_eq!(output, "a [REDACTED] b [REDACTED]");
+        assert_eq!(changes, ["redacted 2"]);
+    }
+
+    #[test]
+    fn output_policy_truncates_on_a_char_boundary() {
+        let policy = OutputPolicy {
+            max_length: Some(4),
+            ..Default::default()
+        };
+        let (output, changes) = apply_output_policy(&policy, "acme/short", "héllo wörld");
+        assert!(output.starts_with("héll\n[... 7 characters truncated by skill 'acme/short'"), "{}", output);
+        assert_eq!(changes, ["truncated 11 to 4"]);
+    }
+
+    #[test]
+    fn output_policy_appends_reminders_and_leaves_short_results_alone() {
+        let policy = OutputPolicy {
+            max_length: Some(100),
+            reminders: vec!["Cite page numbers".to_string()],
+            ..Default::default()
+        };
+        let (output, changes) = apply_output_policy(&policy, "acme/pdf", "page 3");
+        assert_eq!(output, "page 3\n\n[Reminder from skill 'acme/pdf'] Cite page numbers");
+        assert_eq!(changes, ["reminders 1"]);
+
+        let (output, changes) = apply_output_policy(&OutputPolicy::default(), "acme/pdf", "page 3");
+        assert_eq!(output, "page 3");
+        assert!(changes.is_empty());
+    }
+}
@@ -0,0 +1,904 @@
+//! Installing, removing and importing skills, and the local cache of source repositories
+
+use super::{
+    check_reserved_skill_name, check_skill_spec, check_source_policy, discover_skills, find_skill, is_blocked_skill,
+    load_config, load_policy, load_toggles, oversized_script, parse_frontmatter, parse_skill, plugin_dir,
+    policy_violation, resolve_skill, save_toggles, say, sha256_hex, skill_state_file, skill_state_lock, skills_dir,
+    source_cache_dir, take_flag_value, unix_now, update_stats, verify_source_signature, visible_subdirs, ActionError,
+    ErrorKind, Skill,
+};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// ============================================================================
+// Install and Remove
+// ============================================================================
+
+/// Provenance written to `.install.json` in each installed skill directory
+#[derive(Serialize, Deserialize)]
+pub struct InstallRecord {
+    /// Install reference as given, e.g. `owner/name` or `<repo-url>#name`
+    pub source: String,
+    pub url: String,
+    pub commit: Option<String>,
+    pub installed_at: u64,
+    pub installer_version: String,
+    /// Relative path -> SHA-256 of every file as installed
+    pub files: BTreeMap<String, String>,
+}
+
+/// Where an installable skill comes from
+pub struct SkillSource {
+    pub repo_url: String,
+    pub namespace: String,
+    pub name: String,
+}
+
+impl SkillSource {
+    pub fn id(&self) -> String {
+        format!("{}/{}", self.namespace, self.name)
+    }
+}
+
+/// Parse an install reference: `owner/skill-name`, `<repo-url>#skill-name`,
+/// or a URL ending in the skill name
+pub fn parse_skill_ref(skill_ref: &str) -> Result<SkillSource, String> {
+    let (repo_url, namespace, name) = if is_url_ref(skill_ref) {
+        let (url, name) = match skill_ref.split_once('#') {
+            Some((url, name)) => (url, name),
+            None => (
+                skill_ref,
+                skill_ref.trim_end_matches('/').rsplit('/').next().unwrap_or(skill_ref),
+            ),
+        };
+        (url.to_string(), url_namespace(url), name.to_string())
+    } else if skill_ref.contains('/') {
+        let parts: Vec<&str> = skill_ref.splitn(2, '/').collect();
+        let owner = parts[0];
+        let name = parts[1];
+        (
+            format!("https://github.com/{}/skills", owner),
+            sanitize_namespace(owner),
+            name.to_string(),
+        )
+    } else {
+        return Err(format!(
+            "Invalid skill reference '{}'. Use 'owner/skill-name' format.",
+            skill_ref
+        ));
+    };
+
+    if namespace.is_empty() {
+        return Err(format!("Cannot determine the owner of '{}'.", skill_ref));
+    }
+    Ok(SkillSource {
+        repo_url,
+        namespace,
+        name,
+    })
+}
+
+fn is_url_ref(skill_ref: &str) -> bool {
+    skill_ref.starts_with("http") || skill_ref.starts_with("file://")
+}
+
+/// Install skills and their dependencies as one transaction: all of them or none
+pub fn handle_install(skill_refs: &[String], offline: bool) -> Result<serde_json::Value, ActionError> {
+    let mut txn = InstallTransaction::begin()?;
+    for skill_ref in skill_refs {
+        let staged = parse_skill_ref(skill_ref).is_ok_and(|s| txn.is_staged(&s.id()));
+        if staged {
+            continue;
+        }
+        if let Err(e) = install_with_dependencies(skill_ref, offline, &mut Vec::new(), &mut txn) {
+            if !txn.staged.is_empty() {
+                say("Rolled back the install; no skills were changed.");
+            }
+            return Err(e);
+        }
+    }
+
+    let ids: Vec<String> = txn.commit()?.iter().map(|s| s.id()).collect();
+    Ok(serde_json::json!({ "installed": ids }))
+}
+
+/// Skill references from a manifest file: one per line, `#` starts a comment line
+pub fn read_manifest(path: &str) -> Result<Vec<String>, ActionError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| ActionError::new(ErrorKind::Io, format!("Cannot read {}: {}", path, e)))?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+/// Skills checked out and validated, waiting to be moved into place together
+///
+/// Holds the install lock while alive. Dropping it without `commit` discards
+/// everything staged.
+/// Take the lock that serializes installs and removals, failing if another holds it
+///
+/// The holder writes its pid into the lock file, so `doctor` can tell a running
+/// install from an interrupted one without touching the lock.
+fn lock_skills() -> Result<fs::File, ActionError> {
+    let mut lock = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(plugin_dir().join(".install.lock"))
+        .map_err(|e| ActionError::new(ErrorKind::Io, e.to_string()))?;
+    lock.try_lock_exclusive()
+        .map_err(|_| ActionError::new(ErrorKind::Conflict, "Another install or removal is in progress."))?;
+    let _ = lock.set_len(0).and_then(|_| write!(lock, "{}", std::process::id()));
+    Ok(lock)
+}
+
+struct InstallTransaction {
+    dir: PathBuf,
+    staged: Vec<Skill>,
+    _lock: fs::File,
+}
+
+impl InstallTransaction {
+    fn begin() -> Result<Self, ActionError> {
+        let io_error = |e: std::io::Error| ActionError::new(ErrorKind::Io, e.to_string());
+        let lock = lock_skills()?;
+
+        // Holding the lock, anything left in the staging directory is from an interrupted install
+        let dir = skills_dir().join(".install_txn");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).map_err(io_error)?;
+        Ok(InstallTransaction {
+            dir,
+            staged: Vec::new(),
+            _lock: lock,
+        })
+    }
+
+    fn is_staged(&self, id: &str) -> bool {
+        self.staged.iter().any(|s| s.id() == id)
+    }
+
+    fn target_dir(skill: &Skill) -> PathBuf {
+        skills_dir().join(skill.namespace.as_deref().unwrap_or_default()).join(&skill.name)
+    }
+
+    /// Move every staged skill into place, undoing the moves if any of them fails
+    fn commit(mut self) -> Result<Vec<Skill>, ActionError> {
+        let mut committed: Vec<Skill> = Vec::new();
+        for skill in std::mem::take(&mut self.staged) {
+            let target = Self::target_dir(&skill);
+            let moved = match target.parent() {
+                _ if target.exists() => Err(format!("'{}' appeared while installing", target.display())),
+                Some(parent) => fs::create_dir_all(parent)
+                    .and_then(|_| fs::rename(&skill.dir, &target))
+                    .map_err(|e| e.to_string()),
+                None => Err("Invalid skill directory".to_string()),
+            };
+            if let Err(e) = moved {
+                for done in committed.iter().rev() {
+                    let _ = fs::remove_dir_all(&done.dir);
+                    if let Some(ns) = &done.namespace {
+                        let _ = fs::remove_dir(skills_dir().join(ns));
+                    }
+                }
+                return Err(ActionError::new(
+                    ErrorKind::Io,
+                    format!("Error moving skill '{}' into place, rolled back the install: {}", skill.id(), e),
+                ));
+            }
+            committed.push(Skill { dir: target, ..skill });
+        }
+
+        for skill in &committed {
+            say(format!("Successfully installed skill '{}'.", skill.id()));
+            let others: Vec<String> = discover_skills()
+                .iter()
+                .filter(|s| s.name == skill.name && s.id() != skill.id())
+                .map(|s| s.id())
+                .collect();
+            if !others.is_empty() {
+                say(format!(
+                    "Note: '{}' is also installed as {}. Refer to this skill as '{}'.",
+                    skill.name,
+                    others.join(", "),
+                    skill.id()
+                ));
+            }
+        }
+        Ok(committed)
+    }
+}
+
+impl Drop for InstallTransaction {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Provenance file inside an installed skill directory; hidden, so it isn't a skill file
+/// (and not covered by its own hashes)
+const INSTALL_RECORD: &str = ".install.json";
+
+pub fn load_install_record(skill_dir: &Path) -> Option<InstallRecord> {
+    let content = fs::read_to_string(skill_dir.join(INSTALL_RECORD)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// SHA-256 of every file in a skill directory, by relative path
+///
+/// Unlike `skill_files` this includes hidden files, since scripts can run
+/// them too; only the install record itself is left out.
+fn file_hashes(skill_dir: &Path) -> BTreeMap<String, String> {
+    let mut hashes = BTreeMap::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(rel_dir) = pending.pop() {
+        for entry in fs::read_dir(skill_dir.join(&rel_dir)).into_iter().flatten().flatten() {
+            let rel = rel_dir.join(entry.file_name());
+            if rel == Path::new(INSTALL_RECORD) {
+                continue;
+            }
+            if entry.path().is_dir() {
+                pending.push(rel);
+            } else if let Ok(data) = fs::read(entry.path()) {
+                hashes.insert(rel.to_string_lossy().to_string(), sha256_hex(&data));
+            }
+        }
+    }
+    hashes
+}
+
+/// Files added, changed or deleted since install, according to the install record
+pub fn modified_files(skill_dir: &Path, record: &InstallRecord) -> Vec<String> {
+    let current = file_hashes(skill_dir);
+    let mut modified: Vec<String> = current
+        .iter()
+        .filter(|(path, hash)| record.files.get(*path) != Some(hash))
+        .map(|(path, _)| path.clone())
+        .collect();
+    modified.extend(record.files.keys().filter(|p| !current.contains_key(*p)).cloned());
+    modified.sort();
+    modified
+}
+
+/// Stage a skill and, transitively, the skills it lists in `requires-skills`
+///
+/// `chain` holds the ids being installed on the current path, to detect cycles.
+fn install_with_dependencies(
+    skill_ref: &str,
+    offline: bool,
+    chain: &mut Vec<String>,
+    txn: &mut InstallTransaction,
+) -> Result<(), ActionError> {
+    let source = parse_skill_ref(skill_ref).map_err(|e| ActionError::new(ErrorKind::Usage, e))?;
+    let id = source.id();
+
+    let skill = stage_skill(skill_ref, &source, offline, txn)?;
+    chain.push(id.clone());
+
+    for dep in &skill.requires_skills {
+        let dep_id = match parse_skill_ref(dep) {
+            Ok(s) => s.id(),
+            // A bare name can only be satisfied by a skill that is already installed
+            Err(_) if resolve_skill(dep).is_ok() => continue,
+            Err(_) => {
+                return Err(ActionError::new(
+                    ErrorKind::NotFound,
+                    format!(
+                        "'{}' requires skill '{}', which is not installed. Use 'owner/{}' in requires-skills so it can be installed.",
+                        id, dep, dep
+                    ),
+                ));
+            }
+        };
+        if let Some(pos) = chain.iter().position(|c| c == &dep_id) {
+            let mut cycle = chain[pos..].to_vec();
+            cycle.push(dep_id);
+            return Err(ActionError::new(
+                ErrorKind::Conflict,
+                format!("Dependency cycle: {}", cycle.join(" -> ")),
+            ));
+        }
+        if txn.is_staged(&dep_id) || discover_skills().iter().any(|s| s.id() == dep_id) {
+            continue;
+        }
+
+        say(format!("Installing '{}' (required by '{}')...", dep, id));
+        install_with_dependencies(dep, offline, chain, txn).map_err(|e| {
+            ActionError::new(
+                e.kind,
+                format!("Error installing dependency '{}' of '{}': {}", dep, id, e.message),
+            )
+        })?;
+    }
+
+    chain.pop();
+    Ok(())
+}
+
+/// Check a single skill out of the source cache into the transaction's staging directory
+fn stage_skill(
+    skill_ref: &str,
+    source: &SkillSource,
+    offline: bool,
+    txn: &mut InstallTransaction,
+) -> Result<Skill, ActionError> {
+    let skills_dir = skills_dir();
+
+    let SkillSource {
+        repo_url,
+        namespace,
+        name: skill_name,
+    } = source;
+    let policy_error = |e: String| ActionError::new(ErrorKind::Policy, e);
+    check_reserved_skill_name(skill_name).map_err(policy_error)?;
+    let skill_id = source.id();
+    let policy = load_policy().map_err(policy_error)?;
+    check_source_policy(&policy, source).map_err(policy_error)?;
+
+    let target_dir = skills_dir.join(namespace).join(skill_name);
+    if target_dir.exists() {
+        return Err(ActionError::new(
+            ErrorKind::Conflict,
+            format!("Skill '{}' is already installed. Remove it first to reinstall.", skill_id),
+        ));
+    }
+    if skills_dir.join(namespace).join("SKILL.md").exists() {
+        return Err(ActionError::new(
+            ErrorKind::Conflict,
+            format!(
+                "'{}' is an installed skill, so it can't also be used as the namespace for '{}'.",
+                namespace, skill_id
+            ),
+        ));
+    }
+
+    let source_error = |e: String| ActionError::new(ErrorKind::Source, e);
+    let cached_repo = update_source_cache(repo_url, offline).map_err(source_error)?;
+
+    // Clone from the cache with sparse checkout
+    let temp_dir = txn.dir.join(format!(".clone_{}_{}", namespace, skill_name));
+    if temp_dir.exists() {
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    let clone_result = Command::new("git")
+        .args([
+            "clone",
+            "--depth",
+            "1",
+            "--sparse",
+            &format!("file://{}", cached_repo.display()),
+            temp_dir.to_str().unwrap(),
+        ])
+        .output();
+
+    match clone_result {
+        Ok(output) if output.status.success() => {}
+        Ok(output) => {
+            let _ = fs::remove_dir_all(&temp_dir);
+            return Err(source_error(format!(
+                "Error cloning repository: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        Err(e) => {
+            let _ = fs::remove_dir_all(&temp_dir);
+            return Err(source_error(format!("Error running git: {}", e)));
+        }
+    }
+
+    // Set up sparse checkout
+    let _ = Command::new("git")
+        .args([
+            "-C",
+            temp_dir.to_str().unwrap(),
+            "sparse-checkout",
+            "set",
+            &format!("skills/{}", skill_name),
+        ])
+        .output();
+
+    // Move skill to target location
+    let skill_source = temp_dir.join("skills").join(skill_name);
+
+    // The frontmatter name is what the tool is derived from, so check it too
+    let declared_name = fs::read_to_string(skill_source.join("SKILL.md"))
+        .ok()
+        .and_then(|c| parse_frontmatter(&c))
+        .and_then(|f| f.get("name").and_then(|n| n.as_str()).map(|n| n.to_string()));
+    if let Some(Err(e)) = declared_name.as_deref().map(check_reserved_skill_name) {
+        let _ = fs::remove_dir_all(&temp_dir);
+        return Err(policy_error(e));
+    }
+
+    if !skill_source.exists() {
+        let _ = fs::remove_dir_all(&temp_dir);
+        return Err(ActionError::new(
+            ErrorKind::NotFound,
+            format!("Skill '{}' not found in repository.", skill_name),
+        ));
+    }
+
+    let policy_check = if declared_name
+        .as_deref()
+        .is_some_and(|n| is_blocked_skill(&policy, n, &format!("{}/{}", namespace, n)))
+    {
+        Err(format!("Skill '{}' is blocked by policy.", skill_id))
+    } else if let Some(reason) = oversized_script(&policy, &skill_source) {
+        Err(format!("Skill '{}' is not allowed by policy: {}.", skill_id, reason))
+    } else if policy.require_signature {
+        verify_source_signature(&temp_dir)
+    } else {
+        Ok(())
+    };
+    if let Err(e) = policy_check {
+        let _ = fs::remove_dir_all(&temp_dir);
+        return Err(policy_error(e));
+    }
+
+    let commit = Command::new("git")
+        .args(["-C", &temp_dir.to_string_lossy(), "rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string());
+
+    // Validate the staged copy; nothing is installed until the whole transaction commits
+    let staged_dir = txn.dir.join(namespace).join(skill_name);
+    let moved = fs::create_dir_all(txn.dir.join(namespace)).and_then(|_| fs::rename(&skill_source, &staged_dir));
+    let _ = fs::remove_dir_all(&temp_dir);
+    moved.map_err(|e| ActionError::new(ErrorKind::Io, format!("Error staging skill: {}", e)))?;
+
+    let record = InstallRecord {
+        source: skill_ref.to_string(),
+        url: repo_url.clone(),
+        commit,
+        installed_at: unix_now(),
+        installer_version: env!("CARGO_PKG_VERSION").to_string(),
+        files: file_hashes(&staged_dir),
+    };
+    let json = serde_json::to_string_pretty(&record).unwrap();
+    fs::write(staged_dir.join(INSTALL_RECORD), json)
+        .map_err(|e| ActionError::new(ErrorKind::Io, format!("Error writing install record: {}", e)))?;
+
+    let Some(mut skill) = parse_skill(&staged_dir.join("SKILL.md")) else {
+        return Err(source_error(format!("'{}' does not contain a valid SKILL.md.", skill_id)));
+    };
+    skill.namespace = Some(namespace.clone());
+    txn.staged.push(skill.clone());
+    Ok(skill)
+}
+
+pub fn handle_remove(skill_ref: &str, force: bool) -> Result<serde_json::Value, ActionError> {
+    // Held until the skill is gone, so an install can't add a dependent in between
+    let _lock = lock_skills()?;
+
+    // Policy-blocked skills must stay removable
+    let skill = find_skill(&discover_skills(), skill_ref)
+        .cloned()
+        .map_err(|e| ActionError::new(ErrorKind::NotFound, e))?;
+    let id = skill.id();
+
+    let dependents = skill_dependents(&skill);
+    if !dependents.is_empty() && !force {
+        return Err(ActionError::new(
+            ErrorKind::Conflict,
+            format!(
+                "Skill '{}' is required by {}. Remove those first, or use force to remove it anyway.",
+                id,
+                dependents.join(", ")
+            ),
+        ));
+    }
+
+    fs::remove_dir_all(&skill.dir)
+        .map_err(|e| ActionError::new(ErrorKind::Io, format!("Error removing skill: {}", e)))?;
+
+    // Drop the namespace directory once its last skill is gone
+    if let Some(ns_dir) = skill.namespace.as_ref().map(|ns| skills_dir().join(ns)) {
+        let _ = fs::remove_dir(ns_dir);
+    }
+    let mut toggles = load_toggles();
+    toggles.forget(&id);
+    let _ = save_toggles(&toggles);
+    let _ = update_stats(|stats| {
+        stats.remove(&id);
+    });
+    let _ = fs::remove_file(skill_state_file(&id));
+    let _ = fs::remove_file(skill_state_lock(&id));
+    say(format!("Successfully removed skill '{}'.", id));
+    Ok(serde_json::json!({ "removed": id }))
+}
+
+/// Installed skills whose `requires-skills` refer to `skill`
+fn skill_dependents(skill: &Skill) -> Vec<String> {
+    let id = skill.id();
+    discover_skills()
+        .iter()
+        .filter(|other| other.id() != id)
+        .filter(|other| {
+            other.requires_skills.iter().any(|dep| match parse_skill_ref(dep) {
+                Ok(source) => source.id() == id,
+                Err(_) => *dep == skill.name,
+            })
+        })
+        .map(|other| other.id())
+        .collect()
+}
+
+/// Derive a namespace from a skill URL: the repository owner, or the host
+///
+/// Local `file://` repositories take the name of the directory they live in.
+fn url_namespace(url: &str) -> String {
+    if let Some(path) = url.strip_prefix("file://") {
+        let mut segments = path.rsplit('/').filter(|s| !s.is_empty()).skip(1);
+        return sanitize_namespace(segments.next().unwrap_or("local"));
+    }
+    let path = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let mut segments = path.split('/').filter(|s| !s.is_empty());
+    let host = segments.next().unwrap_or_default();
+    sanitize_namespace(segments.next().unwrap_or(host))
+}
+
+/// Namespaces use the skill-name alphabet (no `_`, which separates tool name parts)
+fn sanitize_namespace(raw: &str) -> String {
+    raw.to_ascii_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .trim_matches('-')
+        .to_string()
+}
+
+// ============================================================================
+// Import
+// ============================================================================
+
+/// Skill directories of other agent tools checked by `import`
+const DEFAULT_IMPORT_PATHS: &[&str] = &["~/.claude/skills", "~/.agents/skills", ".claude/skills", ".agents/skills"];
+
+/// Import locations from config; `~` is the home directory and relative paths
+/// are resolved against the working directory (the project)
+fn import_roots() -> Vec<PathBuf> {
+    let config = load_config();
+    let paths: Vec<String> = if config.import_paths.is_empty() {
+        DEFAULT_IMPORT_PATHS.iter().map(|p| p.to_string()).collect()
+    } else {
+        config.import_paths
+    };
+    paths
+        .iter()
+        .map(|p| match p.strip_prefix("~/") {
+            Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+            None => PathBuf::from(p),
+        })
+        .collect()
+}
+
+/// Import skills from other agents' skill directories as top-level skills
+///
+/// Skills are symlinked (or copied) only if they pass the spec check;
+/// `only` limits the import to one skill name.
+pub fn handle_import(only: Option<&str>, copy: bool) -> Result<serde_json::Value, ActionError> {
+    let policy = load_policy().map_err(|e| ActionError::new(ErrorKind::Policy, e))?;
+    let roots = import_roots();
+    let _ = fs::create_dir_all(skills_dir());
+
+    let mut found = false;
+    let mut imported = Vec::new();
+    let mut skipped = Vec::new();
+    let mut skip = |label: String, reason: String| {
+        say(format!("Skipped {}: {}", label, reason));
+        skipped.push(serde_json::json!({ "path": label, "reason": reason }));
+    };
+    for root in &roots {
+        for dir in visible_subdirs(root) {
+            let dir_name = dir.file_name().unwrap_or_default().to_string_lossy().to_string();
+            if !dir.join("SKILL.md").exists() || only.is_some_and(|o| o != dir_name) {
+                continue;
+            }
+            found = true;
+            let label = dir.display().to_string();
+
+            let report = check_skill_spec(&dir);
+            if !report.errors.is_empty() {
+                skip(label, report.errors.join("; "));
+                continue;
+            }
+            let Some(skill) = parse_skill(&dir.join("SKILL.md")) else {
+                skip(label, "SKILL.md could not be parsed".to_string());
+                continue;
+            };
+            if let Some(reason) = policy_violation(&policy, &skill) {
+                skip(label, format!("not allowed by policy: {}", reason));
+                continue;
+            }
+            let target = skills_dir().join(&skill.name);
+            if target.exists() || target.is_symlink() {
+                skip(label, format!("'{}' is already installed", skill.name));
+                continue;
+            }
+
+            let result = if copy {
+                copy_dir_all(&dir, &target)
+            } else {
+                link_dir(&dir, &target)
+            };
+            if let Err(e) = result {
+                skip(label, format!("Error importing: {}", e));
+                continue;
+            }
+            say(format!(
+                "Imported '{}' from {} ({})",
+                skill.name,
+                label,
+                if copy { "copied" } else { "symlinked" }
+            ));
+            for note in &report.unsupported {
+                say(format!("  Unsupported: {}", note));
+            }
+            imported.push(serde_json::json!({
+                "name": skill.name,
+                "path": label,
+                "unsupported": report.unsupported,
+            }));
+        }
+    }
+
+    if !found {
+        let searched: Vec<String> = roots.iter().map(|r| r.display().to_string()).collect();
+        match only {
+            Some(name) => {
+                return Err(ActionError::new(
+                    ErrorKind::NotFound,
+                    format!("Skill '{}' not found in: {}", name, searched.join(", ")),
+                ));
+            }
+            None => say(format!("No skills found in: {}", searched.join(", "))),
+        }
+    }
+    Ok(serde_json::json!({ "imported": imported, "skipped": skipped }))
+}
+
+fn link_dir(source: &Path, target: &Path) -> Result<(), String> {
+    let source = source.canonicalize().map_err(|e| e.to_string())?;
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(&source, target).map_err(|e| e.to_string())
+    }
+    #[cfg(not(unix))]
+    {
+        copy_dir_all(&source, target)
+    }
+}
+
+/// Recursively copy a skill directory, leaving out VCS metadata
+fn copy_dir_all(source: &Path, target: &Path) -> Result<(), String> {
+    fs::create_dir_all(target).map_err(|e| e.to_string())?;
+    for entry in fs::read_dir(source).map_err(|e| e.to_string())?.flatten() {
+        let path = entry.path();
+        if entry.file_name() == ".git" {
+            continue;
+        }
+        let dest = target.join(entry.file_name());
+        if path.is_dir() {
+            copy_dir_all(&path, &dest)?;
+        } else {
+            fs::copy(&path, &dest).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Source Cache
+// ============================================================================
+
+/// Bare repository in the cache that mirrors `repo_url`
+fn cached_repo_path(repo_url: &str) -> PathBuf {
+    let readable = sanitize_namespace(repo_url.split_once("://").map_or(repo_url, |(_, rest)| rest));
+    let readable: String = readable.chars().take(48).collect();
+    let hash = sha256_hex(repo_url.as_bytes());
+    source_cache_dir().join(format!("{}-{}.git", readable, &hash[..8]))
+}
+
+/// Make sure the cache holds `repo_url`, fetching updates unless offline
+///
+/// A failed fetch falls back to the cached copy so installs keep working
+/// while disconnected.
+fn update_source_cache(repo_url: &str, offline: bool) -> Result<PathBuf, String> {
+    let cache = cached_repo_path(repo_url);
+    let cache_str = cache.to_string_lossy().to_string();
+
+    if cache.exists() {
+        if offline {
+            return Ok(cache);
+        }
+        let fetched = Command::new("git")
+            .args([
+                "-C",
+                &cache_str,
+                "fetch",
+                "--depth",
+                "1",
+                "--prune",
+                "origin",
+                "+refs/heads/*:refs/heads/*",
+            ])
+            .output();
+        match fetched {
+            Ok(output) if output.status.success() => {}
+            Ok(output) => say(format!(
+                "Note: Could not update {}, using the cached copy: {}",
+                repo_url,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+            Err(e) => say(format!("Note: Could not update {}, using the cached copy: {}", repo_url, e)),
+        }
+        return Ok(cache);
+    }
+
+    if offline {
+        return Err(format!(
+            "'{}' is not in the source cache. Run 'agent-skills mirror' while online first.",
+            repo_url
+        ));
+    }
+
+    // Clone next to the final path so an interrupted clone never looks cached
+    let _ = fs::create_dir_all(source_cache_dir());
+    let temp = source_cache_dir().join(format!(".tmp_{}", cache.file_name().unwrap_or_default().to_string_lossy()));
+    let _ = fs::remove_dir_all(&temp);
+    let cloned = Command::new("git")
+        .args(["clone", "--bare", "--depth", "1", repo_url, &temp.to_string_lossy()])
+        .output();
+    match cloned {
+        Ok(output) if output.status.success() => {}
+        Ok(output) => {
+            let _ = fs::remove_dir_all(&temp);
+            return Err(format!("Error cloning repository: {}", String::from_utf8_lossy(&output.stderr)));
+        }
+        Err(e) => {
+            let _ = fs::remove_dir_all(&temp);
+            return Err(format!("Error running git: {}", e));
+        }
+    }
+    fs::rename(&temp, &cache).map_err(|e| format!("Error caching {}: {}", repo_url, e))?;
+    Ok(cache)
+}
+
+/// Repository URL for a mirror source: a repo URL, `owner/skill` or just `owner`
+fn mirror_repo_url(source: &str) -> String {
+    if is_url_ref(source) {
+        source.split_once('#').map_or(source, |(url, _)| url).to_string()
+    } else {
+        let owner = source.split('/').next().unwrap_or(source);
+        format!("https://github.com/{}/skills", owner)
+    }
+}
+
+/// Pre-populate the source cache: `mirror <source>... [--from <file>]`
+pub fn handle_mirror_cli(args: &[String]) -> Result<serde_json::Value, ActionError> {
+    let mut args = args.to_vec();
+    let mut sources = Vec::new();
+    if let Some(list) = take_flag_value(&mut args, "--from") {
+        match fs::read_to_string(&list) {
+            Ok(content) => sources.extend(
+                content
+                    .lines()
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                    .map(String::from),
+            ),
+            Err(e) => {
+                return Err(ActionError::new(ErrorKind::Io, format!("Cannot read {}: {}", list, e)));
+            }
+        }
+    }
+    sources.extend(args);
+
+    if sources.is_empty() {
+        return Err(ActionError::new(
+            ErrorKind::Usage,
+            "Usage: agent-skills mirror <owner | owner/skill | repo-url>... [--from <file>]",
+        ));
+    }
+
+    let mut repos: Vec<String> = Vec::new();
+    for repo_url in sources.iter().map(|s| mirror_repo_url(s)) {
+        if !repos.contains(&repo_url) {
+            repos.push(repo_url);
+        }
+    }
+    let (mut mirrored, mut failed) = (Vec::new(), Vec::new());
+    for repo_url in repos {
+        match update_source_cache(&repo_url, false) {
+            Ok(_) => {
+                say(format!("Mirrored {}", repo_url));
+                mirrored.push(repo_url);
+            }
+            Err(e) => {
+                say(format!("Error mirroring {}: {}", repo_url, e));
+                failed.push(repo_url);
+            }
+        }
+    }
+    if failed.is_empty() {
+        Ok(serde_json::json!({ "mirrored": mirrored }))
+    } else {
+        Err(ActionError::new(
+            ErrorKind::Source,
+            format!("Could not mirror {}", failed.join(", ")),
+        ))
+    }
+}
+
+/// A skill found in the source cache, installable as `<repo_url>#<name>`
+pub struct AvailableSkill {
+    pub repo_url: String,
+    pub name: String,
+    pub description: String,
+    /// Raw SKILL.md content
+    pub content: String,
+}
+
+impl AvailableSkill {
+    pub fn install_ref(&self) -> String {
+        format!("{}#{}", self.repo_url, self.name)
+    }
+}
+
+/// Skills under `skills/` in every cached source repository
+pub fn available_skills() -> Vec<AvailableSkill> {
+    let mut available = Vec::new();
+    let repos = fs::read_dir(source_cache_dir())
+        .map(|entries| entries.flatten().map(|e| e.path()).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    for repo in repos.iter().filter(|p| p.extension().is_some_and(|e| e == "git")) {
+        let git = |args: &[&str]| -> Option<String> {
+            let output = Command::new("git")
+                .arg("--git-dir")
+                .arg(repo)
+                .args(args)
+                .output()
+                .ok()?;
+            output
+                .status
+                .success()
+                .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+        };
+        let Some(repo_url) = git(&["config", "--get", "remote.origin.url"]) else {
+            continue;
+        };
+        let Some(listing) = git(&["ls-tree", "--name-only", "HEAD", "skills/"]) else {
+            continue;
+        };
+
+        for name in listing.lines().filter_map(|l| l.strip_prefix("skills/")) {
+            let Some(content) = git(&["show", &format!("HEAD:skills/{}/SKILL.md", name)]) else {
+                continue;
+            };
+            let description = parse_frontmatter(&content)
+                .and_then(|f| f.get("description").and_then(|d| d.as_str()).map(String::from))
+                .unwrap_or_default();
+            available.push(AvailableSkill {
+                repo_url: repo_url.trim().to_string(),
+                name: name.to_string(),
+                description,
+                content,
+            });
+        }
+    }
+
+    available.sort_by(|a, b| a.name.cmp(&b.name).then(a.repo_url.cmp(&b.repo_url)));
+    available
+}
@@ -5,26 +5,144 @@
 //! - Handles skill invocation and progressive disclosure
 //! - Provides marketplace functionality for installing skills
 //! - Enforces allowed-tools restrictions via pre_tool hook
-
+//!
+//! See README.md for the CLI, configuration files and frontmatter extensions.
+
+mod doctor;
+mod hooks;
+mod install;
+mod mcp;
+mod policy;
+mod tui;
+
+use doctor::handle_doctor_cli;
+use hooks::{
+    handle_on_start_hook, handle_post_system_prompt_hook, handle_post_tool_hook, handle_pre_message_hook,
+    handle_pre_tool_hook, merge_hook_responses, run_skill_hooks,
+};
+use install::{
+    available_skills, handle_import, handle_install, handle_mirror_cli, handle_remove, load_install_record,
+    modified_files, parse_skill_ref, read_manifest, AvailableSkill, SkillSource,
+};
+use mcp::run_mcp_server;
+use policy::{
+    check_source_policy, is_blocked_skill, load_policy, oversized_script, policy_violation, verify_source_signature,
+};
+use fs2::FileExt;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::env;
 use std::fs;
-use std::io::{self, Read, Write};
-use std::path::PathBuf;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::{Command, ExitCode};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 // ============================================================================
 // Data Structures
 // ============================================================================
 
 /// Parsed skill from SKILL.md
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 struct Skill {
+    /// Owner or source the skill was installed from (None for top-level skills)
+    namespace: Option<String>,
+    /// Directory containing SKILL.md
+    dir: PathBuf,
     name: String,
     description: String,
     body: String,
     allowed_tools: Option<String>,
+    tools: Vec<SkillTool>,
+    settings: Vec<SkillSetting>,
+    /// `context: fork` runs the skill in its own chibi sub-context
+    fork_context: bool,
+    /// Skills this one builds on (`requires-skills`), as install references
+    requires_skills: Vec<String>,
+    /// chibi tools this skill expects to be available (`requires-tools`)
+    requires_tools: Vec<String>,
+    /// `disclosure: sections` or `full`; falls back to the config default
+    disclosure: Option<String>,
+    /// When an incoming message should bring this skill up (`triggers`)
+    triggers: SkillTriggers,
+    /// chibi hook name -> script run when it fires (`hooks`)
+    hooks: HashMap<String, String>,
+    /// How tool results are shaped while this skill is active (`output-policy`)
+    output_policy: OutputPolicy,
+}
+
+/// Output policy from a skill's `output-policy:` frontmatter
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+struct OutputPolicy {
+    /// Regular expressions whose matches are replaced with `[REDACTED]`
+    redact: Vec<String>,
+    /// Longest tool result, in characters, before it is truncated
+    max_length: Option<usize>,
+    /// Notes appended to every shaped tool result
+    reminders: Vec<String>,
+    /// Only shape results of these tools (default: all but agent-skills' own tools)
+    tools: Vec<String>,
+}
+
+/// Activation triggers from a skill's `triggers:` frontmatter
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+struct SkillTriggers {
+    /// Words matched case-insensitively on word boundaries
+    keywords: Vec<String>,
+    /// Regular expressions matched against the message
+    patterns: Vec<String>,
+    /// Globs matched against file names mentioned in the message
+    files: Vec<String>,
+    /// Activate the skill on a match instead of only suggesting it
+    auto_activate: bool,
+}
+
+/// Typed tool declared in a skill's `tools:` frontmatter, backed by a script
+#[derive(Debug, Clone)]
+struct SkillTool {
+    name: String,
+    description: String,
+    script: String,
+    /// Argument templates passed to the script, e.g. `["--pages", "{pages}"]`
+    args: Vec<String>,
+    /// JSON schema for the tool's parameters
+    parameters: serde_json::Value,
+}
+
+/// User-configurable setting declared in a skill's `settings:` frontmatter
+#[derive(Debug, Clone)]
+struct SkillSetting {
+    name: String,
+    /// One of string, integer, number, boolean
+    setting_type: String,
+    default: Option<serde_json::Value>,
+    /// Secret values live in agent-skills.secrets.json and are redacted from script output
+    secret: bool,
+    description: Option<String>,
+}
+
+/// Plugin configuration loaded from agent-skills.config.json in the plugin directory
+#[derive(Deserialize, Default)]
+struct Config {
+    /// Per-skill setting values: skill id (or name) -> setting name -> value
+    #[serde(default)]
+    settings: HashMap<String, HashMap<String, serde_json::Value>>,
+    /// Path to the chibi binary for `context: fork` skills (default: chibi on PATH)
+    chibi_path: Option<String>,
+    /// Other agents' skill directories to `import` from (default: DEFAULT_IMPORT_PATHS)
+    #[serde(default)]
+    import_paths: Vec<String>,
+    /// Default disclosure mode for skills that don't set one: `full` or `sections`
+    disclosure: Option<String>,
+    /// List recently used skills first in the system prompt
+    #[serde(default)]
+    order_by_usage: bool,
+    /// Days without use before `stats` flags a skill for removal (default 30)
+    stale_after_days: Option<u64>,
 }
 
 /// Active skill state for allowed-tools enforcement
@@ -32,27 +150,64 @@ struct Skill {
 struct ActiveSkill {
     name: String,
     allowed_tools: Option<String>,
+    /// Activated by a trigger rather than a tool call, so its instructions go in the system prompt
+    #[serde(default)]
+    auto_activated: bool,
 }
 
-/// Hook data for pre_tool
-#[derive(Deserialize, Default)]
-struct PreToolHookData {
-    tool_name: Option<String>,
-    #[allow(dead_code)]
-    arguments: Option<serde_json::Value>,
+/// Recorded approval for a skill script (trust-on-first-use)
+#[derive(Serialize, Deserialize, Clone)]
+struct TrustEntry {
+    sha256: String,
+    approved_at: u64,
 }
 
-/// Hook response for blocking a tool
-#[derive(Serialize)]
-struct BlockResponse {
-    block: bool,
-    message: String,
+/// Entry in the append-only audit log (audit.jsonl)
+#[derive(Serialize, Deserialize)]
+struct AuditEntry {
+    timestamp: u64,
+    context: String,
+    skill: String,
+    /// activate, block, script, denied, trigger or output
+    event: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool: Option<String>,
+    /// What matched for trigger events, e.g. `keyword:pdf`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trigger: Option<String>,
+    /// What an output policy changed, for output events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    script: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    script_sha256: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    args_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exit_code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<u64>,
 }
 
-/// Hook response for injecting content
-#[derive(Serialize)]
-struct InjectResponse {
-    inject: String,
+/// Usage counters for one skill (.skill_stats.json)
+#[derive(Serialize, Deserialize, Default, Clone)]
+struct SkillStats {
+    invocations: u64,
+    script_runs: u64,
+    failures: u64,
+    last_used: Option<u64>,
+}
+
+/// Enabled/disabled state for installed skills (.skill_toggles.json)
+#[derive(Serialize, Deserialize, Default)]
+struct SkillToggles {
+    /// Skills disabled in every context
+    #[serde(default)]
+    disabled: Vec<String>,
+    /// Per-context overrides: context -> skill id -> enabled
+    #[serde(default)]
+    contexts: HashMap<String, HashMap<String, bool>>,
 }
 
 /// Tool arguments for skill_marketplace
@@ -60,7 +215,19 @@ struct InjectResponse {
 struct MarketplaceArgs {
     action: Option<String>,
     skill_ref: Option<String>,
+    /// More skills to install in the same transaction
+    skill_refs: Option<Vec<String>>,
+    /// File listing skills to install, one reference per line (CLI only)
+    manifest: Option<String>,
     query: Option<String>,
+    /// Limit enable/disable to a single context
+    context: Option<String>,
+    /// Remove a skill even if other installed skills require it
+    force: Option<bool>,
+    /// Install only from the local source cache, without network access
+    offline: Option<bool>,
+    /// Import by copying instead of symlinking
+    copy: Option<bool>,
 }
 
 /// Tool arguments for read_skill_file
@@ -70,6 +237,54 @@ struct ReadSkillFileArgs {
     path: Option<String>,
 }
 
+/// Tool arguments for read_skill_section
+#[derive(Deserialize, Default)]
+struct ReadSkillSectionArgs {
+    skill: Option<String>,
+    anchor: Option<String>,
+}
+
+/// Tool arguments for copy_skill_asset
+#[derive(Deserialize, Default)]
+struct CopySkillAssetArgs {
+    skill: Option<String>,
+    /// File or directory relative to the skill's `assets/`
+    path: Option<String>,
+    /// Destination relative to the working directory (default: the asset's name)
+    destination: Option<String>,
+    /// Values for `{{name}}` placeholders in text files
+    variables: Option<HashMap<String, String>>,
+    overwrite: Option<bool>,
+}
+
+/// Tool arguments for skill_state
+#[derive(Deserialize, Default)]
+struct SkillStateArgs {
+    skill: Option<String>,
+    /// get, set, delete or list
+    operation: Option<String>,
+    key: Option<String>,
+    /// A JSON `null` is a value to store, so only a missing field is None
+    #[serde(default, deserialize_with = "present_value")]
+    value: Option<serde_json::Value>,
+    /// `skill` (default) or `context` for values kept per chibi context
+    scope: Option<String>,
+}
+
+fn present_value<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<serde_json::Value>, D::Error> {
+    serde_json::Value::deserialize(deserializer).map(Some)
+}
+
+/// A skill's persistent key-value store (state/<skill>.json)
+#[derive(Serialize, Deserialize, Default)]
+struct SkillState {
+    #[serde(default)]
+    values: HashMap<String, serde_json::Value>,
+    /// Per-context values: context name -> key -> value
+    #[serde(default)]
+    contexts: HashMap<String, HashMap<String, serde_json::Value>>,
+}
+
 /// Tool arguments for run_skill_script
 #[derive(Deserialize, Default)]
 struct RunSkillScriptArgs {
@@ -88,8 +303,16 @@ struct SkillInvocationArgs {
 /// Installed skill info for listing
 #[derive(Serialize)]
 struct SkillInfo {
+    id: String,
     name: String,
     description: String,
+    enabled: bool,
+    /// Where the skill was installed from (None if placed by hand or imported)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    /// Whether files changed since install (None without an install record)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    modified: Option<bool>,
 }
 
 // ============================================================================
@@ -123,6 +346,48 @@ fn state_file() -> PathBuf {
     plugin_dir().join(".active_skill.json")
 }
 
+fn state_dir() -> PathBuf {
+    plugin_dir().join("state")
+}
+
+fn stats_file() -> PathBuf {
+    plugin_dir().join(".skill_stats.json")
+}
+
+fn toggles_file() -> PathBuf {
+    plugin_dir().join(".skill_toggles.json")
+}
+
+fn trust_file() -> PathBuf {
+    plugin_dir().join(".trust.json")
+}
+
+fn audit_file() -> PathBuf {
+    plugin_dir().join("audit.jsonl")
+}
+
+fn chibi_dir() -> Result<PathBuf, String> {
+    dirs::home_dir()
+        .map(|home| home.join(".chibi"))
+        .ok_or_else(|| "Could not find home directory".to_string())
+}
+
+fn source_cache_dir() -> PathBuf {
+    plugin_dir().join("cache")
+}
+
+fn policy_file() -> PathBuf {
+    plugin_dir().join("policy.json")
+}
+
+fn config_file() -> PathBuf {
+    plugin_dir().join("agent-skills.config.json")
+}
+
+fn secrets_file() -> PathBuf {
+    plugin_dir().join("agent-skills.secrets.json")
+}
+
 // ============================================================================
 // SKILL.md Parsing
 // ============================================================================
@@ -162,6 +427,9 @@ fn parse_skill(skill_path: &PathBuf) -> Option<Skill> {
     if name.len() > 64 || description.len() > 1024 {
         return None;
     }
+    if check_reserved_skill_name(&name).is_err() {
+        return None;
+    }
 
     // Extract body (everything after frontmatter)
     let body_start = content.find("\n---\n").map(|p| p + 5).unwrap_or(0);
@@ -173,448 +441,1994 @@ fn parse_skill(skill_path: &PathBuf) -> Option<Skill> {
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
 
+    let tools = frontmatter
+        .get("tools")
+        .map(|v| parse_skill_tools(&name, v))
+        .unwrap_or_default();
+
+    let settings = frontmatter
+        .get("settings")
+        .map(parse_skill_settings)
+        .unwrap_or_default();
+
+    let fork_context = frontmatter.get("context").and_then(|v| v.as_str()) == Some("fork");
+    let requires_skills = frontmatter_string_list(frontmatter.get("requires-skills"));
+    let requires_tools = frontmatter_string_list(frontmatter.get("requires-tools"));
+    let triggers = frontmatter
+        .get("triggers")
+        .and_then(|v| serde_yaml::from_value(v.clone()).ok())
+        .unwrap_or_default();
+    let hooks = frontmatter.get("hooks").map(parse_skill_hooks).unwrap_or_default();
+    let output_policy = frontmatter
+        .get("output-policy")
+        .and_then(|v| serde_yaml::from_value(v.clone()).ok())
+        .unwrap_or_default();
+    let disclosure = frontmatter
+        .get("disclosure")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
     Some(Skill {
+        namespace: None,
+        dir: skill_path.parent().map(|p| p.to_path_buf()).unwrap_or_default(),
         name,
         description,
         body,
         allowed_tools,
+        tools,
+        settings,
+        fork_context,
+        requires_skills,
+        requires_tools,
+        disclosure,
+        triggers,
+        hooks,
+        output_policy,
     })
 }
 
-/// List all valid skills in the skills directory
-fn list_skills() -> Vec<Skill> {
-    let mut skills = Vec::new();
-    let dir = skills_dir();
+/// Parse `hooks:` frontmatter (hook name -> script), keeping known chibi hooks only
+fn parse_skill_hooks(value: &serde_yaml::Value) -> HashMap<String, String> {
+    let Some(map) = value.as_mapping() else {
+        return HashMap::new();
+    };
+    map.iter()
+        .filter_map(|(k, v)| Some((k.as_str()?.to_string(), v.as_str()?.to_string())))
+        .filter(|(hook, _)| CHIBI_HOOKS.contains(&hook.as_str()))
+        .collect()
+}
+
+/// Frontmatter fields defined by the Agent Skills spec
+const SPEC_FIELDS: &[&str] = &["name", "description", "license", "allowed-tools", "metadata", "compatibility"];
+
+/// chibi's own frontmatter extensions
+const EXTENSION_FIELDS: &[&str] = &[
+    "tools",
+    "settings",
+    "context",
+    "requires-skills",
+    "requires-tools",
+    "disclosure",
+    "triggers",
+    "hooks",
+    "output-policy",
+];
+
+/// Result of checking a skill directory against the Agent Skills spec
+#[derive(Default)]
+struct SpecReport {
+    /// Spec violations; the skill can't be used as is
+    errors: Vec<String>,
+    /// Fields chibi doesn't support; the skill works but they are ignored
+    unsupported: Vec<String>,
+}
+
+/// Check a skill directory against the Agent Skills spec
+///
+/// Stricter than `parse_skill`, which only rejects what it can't index.
+fn check_skill_spec(skill_dir: &Path) -> SpecReport {
+    let mut report = SpecReport::default();
+    let content = match fs::read_to_string(skill_dir.join("SKILL.md")) {
+        Ok(c) => c,
+        Err(e) => {
+            report.errors.push(format!("cannot read SKILL.md: {}", e));
+            return report;
+        }
+    };
+    let Some(frontmatter) = parse_frontmatter(&content) else {
+        report.errors.push("missing or invalid YAML frontmatter".to_string());
+        return report;
+    };
+
+    match frontmatter.get("name").and_then(|v| v.as_str()) {
+        None => report.errors.push("missing required field 'name'".to_string()),
+        Some(name) => {
+            if name.is_empty() || name.len() > 64 {
+                report.errors.push("name must be 1-64 characters".to_string());
+            }
+            if !name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-') {
+                report
+                    .errors
+                    .push("name may only contain lowercase letters, digits and hyphens".to_string());
+            }
+            if name.starts_with('-') || name.ends_with('-') || name.contains("--") {
+                report
+                    .errors
+                    .push("name must not start or end with a hyphen or contain '--'".to_string());
+            }
+            let dir_name = skill_dir.file_name().unwrap_or_default().to_string_lossy();
+            if name != dir_name {
+                report
+                    .errors
+                    .push(format!("name '{}' does not match directory '{}'", name, dir_name));
+            }
+            if let Err(e) = check_reserved_skill_name(name) {
+                report.errors.push(e);
+            }
+        }
+    }
 
-    if !dir.exists() {
-        return skills;
+    match frontmatter.get("description").and_then(|v| v.as_str()) {
+        None => report.errors.push("missing required field 'description'".to_string()),
+        Some(d) if d.trim().is_empty() || d.len() > 1024 => {
+            report.errors.push("description must be 1-1024 characters".to_string())
+        }
+        Some(_) => {}
+    }
+    if let Some(c) = frontmatter.get("compatibility") {
+        if c.as_str().is_none_or(|c| c.len() > 500) {
+            report
+                .errors
+                .push("compatibility must be a string of at most 500 characters".to_string());
+        }
     }
 
-    if let Ok(entries) = fs::read_dir(&dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.is_dir() && !path.file_name().map_or(true, |n| n.to_string_lossy().starts_with('.')) {
-                let skill_path = path.join("SKILL.md");
-                if let Some(skill) = parse_skill(&skill_path) {
-                    skills.push(skill);
+    if frontmatter.get("allowed-tools").is_some_and(|v| !v.is_string()) {
+        report
+            .unsupported
+            .push("allowed-tools: only a comma-separated string is supported".to_string());
+    }
+    if frontmatter.get("context").is_some_and(|v| v.as_str() != Some("fork")) {
+        report.unsupported.push("context: only 'fork' is supported".to_string());
+    }
+    if frontmatter
+        .get("disclosure")
+        .is_some_and(|v| !matches!(v.as_str(), Some("sections" | "full")))
+    {
+        report
+            .unsupported
+            .push("disclosure: only 'sections' or 'full' is supported".to_string());
+    }
+    if let Some(hooks) = frontmatter.get("hooks") {
+        match hooks.as_mapping() {
+            Some(map) => {
+                for (hook, script) in map {
+                    let hook = hook.as_str().unwrap_or_default();
+                    if !CHIBI_HOOKS.contains(&hook) {
+                        report.unsupported.push(format!("hooks: unknown chibi hook '{}'", hook));
+                    } else if !script.is_string() {
+                        report
+                            .unsupported
+                            .push(format!("hooks: '{}' must name a single script", hook));
+                    }
+                }
+            }
+            None => report
+                .unsupported
+                .push("hooks: only a mapping of hook name to script is supported".to_string()),
+        }
+    }
+    if let Some(policy) = frontmatter.get("output-policy") {
+        match serde_yaml::from_value::<OutputPolicy>(policy.clone()) {
+            Ok(p) => {
+                for pattern in &p.redact {
+                    if let Err(e) = regex::Regex::new(pattern) {
+                        report.errors.push(format!("invalid redact pattern '{}': {}", pattern, e));
+                    }
+                }
+            }
+            Err(e) => report.errors.push(format!("invalid output-policy: {}", e)),
+        }
+    }
+    if let Some(triggers) = frontmatter.get("triggers") {
+        match serde_yaml::from_value::<SkillTriggers>(triggers.clone()) {
+            Ok(t) => {
+                for pattern in &t.patterns {
+                    if let Err(e) = regex::Regex::new(pattern) {
+                        report.errors.push(format!("invalid trigger pattern '{}': {}", pattern, e));
+                    }
+                }
+                for glob in &t.files {
+                    if let Err(e) = glob::Pattern::new(glob) {
+                        report.errors.push(format!("invalid trigger file glob '{}': {}", glob, e));
+                    }
                 }
             }
+            Err(e) => report.errors.push(format!("invalid triggers: {}", e)),
         }
     }
+    let mut unknown: Vec<&String> = frontmatter
+        .keys()
+        .filter(|k| !SPEC_FIELDS.contains(&k.as_str()) && !EXTENSION_FIELDS.contains(&k.as_str()))
+        .collect();
+    unknown.sort();
+    for key in unknown {
+        report.unsupported.push(format!("{}: not supported by chibi, ignored", key));
+    }
 
-    skills.sort_by(|a, b| a.name.cmp(&b.name));
-    skills
+    report
 }
 
-// ============================================================================
-// State Management
-// ============================================================================
-
-fn get_active_skill() -> Option<ActiveSkill> {
-    let path = state_file();
-    if !path.exists() {
-        return None;
+/// Read a frontmatter field given either as a YAML list or a comma-separated string
+fn frontmatter_string_list(value: Option<&serde_yaml::Value>) -> Vec<String> {
+    match value {
+        Some(serde_yaml::Value::Sequence(items)) => items
+            .iter()
+            .filter_map(|v| v.as_str())
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect(),
+        Some(serde_yaml::Value::String(s)) => s
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect(),
+        _ => Vec::new(),
     }
-    let content = fs::read_to_string(&path).ok()?;
-    serde_json::from_str(&content).ok()
 }
 
-fn set_active_skill(name: &str, allowed_tools: Option<String>) {
-    let state = ActiveSkill {
-        name: name.to_string(),
-        allowed_tools,
+/// Parse the `settings:` frontmatter section
+///
+/// ```yaml
+/// settings:
+///   api-key:
+///     type: string
+///     secret: true
+///     description: API key for the service
+///   dpi:
+///     type: integer
+///     default: 150
+/// ```
+fn parse_skill_settings(value: &serde_yaml::Value) -> Vec<SkillSetting> {
+    let mut settings = Vec::new();
+    let Some(mapping) = value.as_mapping() else {
+        return settings;
     };
-    if let Ok(json) = serde_json::to_string(&state) {
-        let _ = fs::write(state_file(), json);
+
+    for (key, decl) in mapping {
+        let Some(name) = key.as_str() else { continue };
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            continue;
+        }
+
+        let setting_type = decl
+            .get("type")
+            .and_then(|v| v.as_str())
+            .unwrap_or("string")
+            .to_string();
+        if !matches!(setting_type.as_str(), "string" | "integer" | "number" | "boolean") {
+            continue;
+        }
+
+        settings.push(SkillSetting {
+            name: name.to_string(),
+            setting_type,
+            default: decl.get("default").and_then(|v| serde_json::to_value(v).ok()),
+            secret: decl.get("secret").and_then(|v| v.as_bool()).unwrap_or(false),
+            description: decl
+                .get("description")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string()),
+        });
     }
+
+    settings.sort_by(|a, b| a.name.cmp(&b.name));
+    settings
 }
 
-fn clear_active_skill() {
-    let path = state_file();
-    if path.exists() {
-        let _ = fs::remove_file(path);
+/// Parse the `tools:` frontmatter section into typed tool declarations
+///
+/// Expected shape:
+/// ```yaml
+/// tools:
+///   extract:
+///     description: Extract pages from a PDF
+///     script: scripts/extract.py
+///     args: ["--pages", "{pages}", "{file}"]
+///     parameters:
+///       type: object
+///       properties:
+///         pages: {type: integer}
+///         file: {type: string}
+///       required: [file]
+/// ```
+/// Invalid entries are skipped so one bad declaration doesn't hide the skill.
+fn parse_skill_tools(skill_name: &str, value: &serde_yaml::Value) -> Vec<SkillTool> {
+    let mut tools = Vec::new();
+    let Some(mapping) = value.as_mapping() else {
+        return tools;
+    };
+
+    for (key, decl) in mapping {
+        let Some(name) = key.as_str() else { continue };
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        {
+            continue;
+        }
+        let Some(script) = decl.get("script").and_then(|v| v.as_str()) else {
+            continue;
+        };
+
+        let description = decl
+            .get("description")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| format!("Run {} from skill '{}'", script, skill_name));
+
+        let args = decl
+            .get("args")
+            .and_then(|v| v.as_sequence())
+            .map(|seq| {
+                seq.iter()
+                    .filter_map(|a| match a {
+                        serde_yaml::Value::String(s) => Some(s.clone()),
+                        serde_yaml::Value::Number(n) => Some(n.to_string()),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let parameters = decl
+            .get("parameters")
+            .and_then(|v| serde_json::to_value(v).ok())
+            .filter(|v| v.is_object())
+            .unwrap_or_else(|| serde_json::json!({"type": "object", "properties": {}}));
+
+        tools.push(SkillTool {
+            name: name.to_string(),
+            description,
+            script: script.to_string(),
+            args,
+            parameters,
+        });
     }
+
+    tools.sort_by(|a, b| a.name.cmp(&b.name));
+    tools
 }
 
-// ============================================================================
-// Allowed Tools Checking
-// ============================================================================
+/// List the skills that are installed and allowed by the admin policy
+fn list_skills() -> Vec<Skill> {
+    let policy = load_policy();
+    discover_skills()
+        .into_iter()
+        .filter(|s| match &policy {
+            Ok(policy) => policy_violation(policy, s).is_none(),
+            Err(_) => false,
+        })
+        .collect()
+}
 
-fn is_tool_allowed(tool_name: &str, allowed_tools: &str) -> bool {
-    // Parse allowed-tools string: "Read, Grep, Bash(git:*)"
-    let allowed_list: Vec<&str> = allowed_tools.split(',').map(|s| s.trim()).collect();
+/// List all valid skills in the skills directory, regardless of policy
+///
+/// Skills live either directly under the skills directory or one level down
+/// in a namespace directory named after the owner/source they came from.
+fn discover_skills() -> Vec<Skill> {
+    let mut skills = Vec::new();
 
-    for allowed in allowed_list {
-        if allowed.contains('(') {
-            // Pattern match: Bash(git:*) - for now, allow if base matches
-            let base = allowed.split('(').next().unwrap_or("");
-            if tool_name == base {
-                return true;
+    for path in visible_subdirs(&skills_dir()) {
+        let skill_path = path.join("SKILL.md");
+        if skill_path.exists() {
+            if let Some(skill) = parse_skill(&skill_path) {
+                skills.push(skill);
+            }
+            continue;
+        }
+
+        // A directory without SKILL.md is a namespace
+        let namespace = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        for skill_dir in visible_subdirs(&path) {
+            if let Some(mut skill) = parse_skill(&skill_dir.join("SKILL.md")) {
+                skill.namespace = Some(namespace.clone());
+                skills.push(skill);
             }
-        } else if tool_name == allowed {
-            return true;
         }
     }
 
-    // Always allow agent-skills tools themselves
-    if matches!(
-        tool_name,
-        "skill_marketplace" | "read_skill_file" | "run_skill_script"
-    ) {
-        return true;
+    skills.sort_by_key(|s| s.id());
+    skills
+}
+
+/// Subdirectories of `dir`, skipping hidden ones (e.g. `.tmp_*` install staging)
+fn visible_subdirs(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| {
+                    p.is_dir() && !p.file_name().is_none_or(|n| n.to_string_lossy().starts_with('.'))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    dirs.sort();
+    dirs
+}
+
+impl Skill {
+    /// Qualified identifier: `namespace/name`, or just `name` for top-level skills
+    fn id(&self) -> String {
+        match &self.namespace {
+            Some(ns) => format!("{}/{}", ns, self.name),
+            None => self.name.clone(),
+        }
     }
-    if tool_name.starts_with("skill_") {
-        return true;
+}
+
+/// Find a skill by qualified id (`owner/name`) or by short name when unambiguous
+fn find_skill<'a>(skills: &'a [Skill], reference: &str) -> Result<&'a Skill, String> {
+    if let Some(skill) = skills.iter().find(|s| s.id() == reference) {
+        return Ok(skill);
     }
 
-    false
+    let matches: Vec<&Skill> = skills.iter().filter(|s| s.name == reference).collect();
+    match matches.as_slice() {
+        [] => Err(format!("Skill '{}' not found", reference)),
+        [skill] => Ok(skill),
+        _ => Err(format!(
+            "Skill name '{}' is ambiguous, use one of: {}",
+            reference,
+            matches.iter().map(|s| s.id()).collect::<Vec<_>>().join(", ")
+        )),
+    }
+}
+
+/// Resolve an installed skill reference to its parsed skill
+fn resolve_skill(reference: &str) -> Result<Skill, String> {
+    find_skill(&list_skills(), reference).cloned().map_err(|e| {
+        // Explain skills hidden by the policy instead of reporting them missing
+        match (find_skill(&discover_skills(), reference), load_policy()) {
+            (Ok(skill), Ok(policy)) => match policy_violation(&policy, skill) {
+                Some(reason) => format!("Skill '{}' is blocked by policy: {}", skill.id(), reason),
+                None => e,
+            },
+            (Ok(_), Err(policy_err)) => policy_err,
+            (Err(_), _) => e,
+        }
+    })
 }
 
 // ============================================================================
-// Schema Generation
+// Skill Tool Names
 // ============================================================================
 
-fn generate_schema() -> serde_json::Value {
-    let mut tools = Vec::new();
+/// Longest tool name chibi (and the OpenAI-style APIs behind it) accepts
+const MAX_TOOL_NAME_LEN: usize = 64;
 
-    // Core management tools
-    tools.push(serde_json::json!({
-        "name": "skill_marketplace",
-        "description": "Install, remove, search, or list Agent Skills from the marketplace",
-        "parameters": {
-            "type": "object",
-            "properties": {
-                "action": {
-                    "type": "string",
-                    "enum": ["install", "remove", "search", "list", "list_installed"],
-                    "description": "Action to perform"
-                },
-                "skill_ref": {
-                    "type": "string",
-                    "description": "Skill reference (owner/name) for install/remove"
-                },
-                "query": {
-                    "type": "string",
-                    "description": "Search query for search action"
-                }
-            },
-            "required": ["action"]
-        },
-        "hooks": ["post_system_prompt", "pre_tool", "on_start"]
-    }));
+/// Tool exposed for an installed skill: the skill itself or one of its typed tools
+struct SkillToolRef<'a> {
+    tool_name: String,
+    skill: &'a Skill,
+    typed: Option<&'a SkillTool>,
+}
 
-    tools.push(serde_json::json!({
-        "name": "read_skill_file",
-        "description": "Read a file from an installed skill's directory (scripts, references, etc.)",
-        "parameters": {
-            "type": "object",
-            "properties": {
-                "skill": {
-                    "type": "string",
-                    "description": "Name of the installed skill"
-                },
-                "path": {
-                    "type": "string",
-                    "description": "Relative path to the file within the skill directory"
-                }
-            },
-            "required": ["skill", "path"]
+/// Base tool name for a skill
+///
+/// `skill_<name>` while the name is unique across installed skills, otherwise
+/// `skill_<namespace>_<name>`. Neither part can contain `_`, so the qualified
+/// form never collides with a short one.
+fn skill_tool_base(skill: &Skill, skills: &[Skill]) -> String {
+    let shared = skills.iter().filter(|s| s.name == skill.name).count() > 1;
+    match &skill.namespace {
+        Some(ns) if shared => format!("skill_{}_{}", ns, skill.name),
+        _ => format!("skill_{}", skill.name),
+    }
+}
+
+/// Keep a tool name within MAX_TOOL_NAME_LEN, staying unique via a hash of `key`
+fn clamp_tool_name(name: String, key: &str) -> String {
+    if name.len() <= MAX_TOOL_NAME_LEN {
+        return name;
+    }
+    let suffix = &sha256_hex(key.as_bytes())[..8];
+    format!("{}_{}", &name[..MAX_TOOL_NAME_LEN - suffix.len() - 1], suffix)
+}
+
+/// Derive tool names for every skill and typed tool in `skills`
+fn skill_tool_refs(skills: &[Skill]) -> Vec<SkillToolRef<'_>> {
+    let mut refs = Vec::new();
+    for skill in skills {
+        let base = skill_tool_base(skill, skills);
+        let id = skill.id();
+        refs.push(SkillToolRef {
+            tool_name: clamp_tool_name(base.clone(), &id),
+            skill,
+            typed: None,
+        });
+        for tool in &skill.tools {
+            refs.push(SkillToolRef {
+                tool_name: clamp_tool_name(
+                    format!("{}__{}", base, tool.name),
+                    &format!("{}__{}", id, tool.name),
+                ),
+                skill,
+                typed: Some(tool),
+            });
         }
-    }));
+    }
+    refs
+}
 
-    tools.push(serde_json::json!({
-        "name": "run_skill_script",
-        "description": "Execute a script from an installed skill's directory (e.g., scripts/extract.py)",
-        "parameters": {
-            "type": "object",
-            "properties": {
-                "skill": {
-                    "type": "string",
-                    "description": "Name of the installed skill"
-                },
-                "script": {
-                    "type": "string",
-                    "description": "Relative path to the script within the skill directory"
-                },
-                "args": {
-                    "type": "array",
-                    "items": {"type": "string"},
-                    "description": "Arguments to pass to the script (optional)"
-                },
-                "stdin": {
-                    "type": "string",
-                    "description": "Input to pass to the script via stdin (optional)"
-                }
-            },
-            "required": ["skill", "script"]
+// ============================================================================
+// Enable / Disable
+// ============================================================================
+
+impl SkillToggles {
+    /// A per-context override wins over the global disabled list
+    fn is_enabled(&self, skill_name: &str, context: &str) -> bool {
+        if let Some(enabled) = self.contexts.get(context).and_then(|c| c.get(skill_name)) {
+            return *enabled;
         }
-    }));
+        !self.disabled.iter().any(|d| d == skill_name)
+    }
 
-    // One tool per installed skill
-    for skill in list_skills() {
-        tools.push(serde_json::json!({
-            "name": format!("skill_{}", skill.name),
-            "description": skill.description,
-            "parameters": {
-                "type": "object",
-                "properties": {
-                    "arguments": {
-                        "type": "string",
-                        "description": "Arguments to pass to the skill (optional)"
-                    }
+    fn set(&mut self, skill_name: &str, enabled: bool, context: Option<&str>) {
+        match context {
+            Some(ctx) => {
+                self.contexts
+                    .entry(ctx.to_string())
+                    .or_default()
+                    .insert(skill_name.to_string(), enabled);
+            }
+            None => {
+                self.disabled.retain(|d| d != skill_name);
+                if !enabled {
+                    self.disabled.push(skill_name.to_string());
+                    self.disabled.sort();
                 }
             }
-        }));
+        }
     }
 
-    serde_json::Value::Array(tools)
+    /// Drop all state for a skill (used when it is removed)
+    fn forget(&mut self, skill_name: &str) {
+        self.disabled.retain(|d| d != skill_name);
+        for overrides in self.contexts.values_mut() {
+            overrides.remove(skill_name);
+        }
+        self.contexts.retain(|_, overrides| !overrides.is_empty());
+    }
+}
+
+fn load_toggles() -> SkillToggles {
+    fs::read_to_string(toggles_file())
+        .ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+fn save_toggles(toggles: &SkillToggles) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(toggles).map_err(|e| e.to_string())?;
+    fs::write(toggles_file(), json).map_err(|e| format!("Failed to write skill state: {}", e))
+}
+
+/// Whether a skill is enabled in the current context
+fn is_skill_enabled(skill_name: &str) -> bool {
+    load_toggles().is_enabled(skill_name, &env::var("CHIBI_CONTEXT").unwrap_or_default())
 }
 
 // ============================================================================
-// Hook Handlers
+// Configuration and Secrets
 // ============================================================================
 
-fn handle_on_start_hook() {
-    clear_active_skill();
-    println!("{{}}");
+fn load_config() -> Config {
+    if let Ok(content) = fs::read_to_string(config_file()) {
+        serde_json::from_str(&content).unwrap_or_default()
+    } else {
+        Config::default()
+    }
 }
 
-fn handle_post_system_prompt_hook() {
-    let skills = list_skills();
-    if skills.is_empty() {
-        println!("{{}}");
-        return;
+/// Load secret setting values, refusing files readable by other users
+fn load_secrets() -> Result<HashMap<String, HashMap<String, serde_json::Value>>, String> {
+    let path = secrets_file();
+    if !path.exists() {
+        return Ok(HashMap::new());
     }
 
-    let mut lines = vec!["## Available Agent Skills".to_string(), String::new()];
-    for skill in skills {
-        lines.push(format!("- **{}**: {}", skill.name, skill.description));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?
+            .permissions()
+            .mode();
+        if mode & 0o077 != 0 {
+            return Err(format!(
+                "{} must not be accessible by other users (mode {:o}). Run: chmod 600 {}",
+                path.display(),
+                mode & 0o777,
+                path.display()
+            ));
+        }
     }
-    lines.push(String::new());
-    lines.push("Use skill_[name] tools to invoke a skill and receive detailed instructions.".to_string());
 
-    let response = InjectResponse {
-        inject: lines.join("\n"),
-    };
-    println!("{}", serde_json::to_string(&response).unwrap());
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Invalid {}: {}", path.display(), e))
 }
 
-fn handle_pre_tool_hook(stdin_data: &str) {
-    let hook_data: PreToolHookData = serde_json::from_str(stdin_data).unwrap_or_default();
+/// Environment variable name for a setting: `api-key` -> `SKILL_SETTING_API_KEY`
+///
+/// The prefix keeps settings from overriding `PATH`, `HOME`, `LD_PRELOAD` and the like.
+fn setting_env_name(name: &str) -> String {
+    format!("SKILL_SETTING_{}", name.to_ascii_uppercase().replace('-', "_"))
+}
 
-    let tool_name = hook_data.tool_name.unwrap_or_default();
+/// Resolved environment for a skill's scripts
+struct SkillEnv {
+    vars: Vec<(String, String)>,
+    /// Secret values to redact from output returned to the model
+    secrets: Vec<String>,
+}
 
-    // Track skill activation
-    if tool_name.starts_with("skill_") && tool_name != "skill_marketplace" {
-        let skill_name = &tool_name[6..]; // Remove "skill_" prefix
-        let skill_path = skills_dir().join(skill_name).join("SKILL.md");
-        if let Some(skill) = parse_skill(&skill_path) {
-            set_active_skill(skill_name, skill.allowed_tools);
-            println!("{{}}");
-            return;
-        }
+/// Resolve a skill's declared settings from the secrets file, the config file and defaults
+fn resolve_skill_env(skill: &Skill) -> Result<SkillEnv, String> {
+    let mut env = SkillEnv {
+        vars: Vec::new(),
+        secrets: Vec::new(),
+    };
+    if skill.settings.is_empty() {
+        return Ok(env);
     }
 
-    // Enforce allowed-tools for active skill
-    if let Some(active) = get_active_skill() {
-        if let Some(allowed) = &active.allowed_tools {
-            if !is_tool_allowed(&tool_name, allowed) {
-                let response = BlockResponse {
-                    block: true,
-                    message: format!(
-                        "Tool '{}' is not allowed while skill '{}' is active. Allowed tools: {}",
-                        tool_name, active.name, allowed
-                    ),
-                };
-                println!("{}", serde_json::to_string(&response).unwrap());
-                return;
-            }
+    let config = load_config();
+    let secrets = load_secrets()?;
+    let id = skill.id();
+    let configured = config.settings.get(&id).or_else(|| config.settings.get(&skill.name));
+    let secret_values = secrets.get(&id).or_else(|| secrets.get(&skill.name));
+
+    for setting in &skill.settings {
+        let value = secret_values
+            .and_then(|v| v.get(&setting.name))
+            .or_else(|| configured.and_then(|v| v.get(&setting.name)))
+            .or(setting.default.as_ref());
+
+        let Some(value) = value else { continue };
+        if !json_type_matches(value, &setting.setting_type) {
+            return Err(format!(
+                "Setting '{}' of skill '{}' must be of type {}, got {}",
+                setting.name,
+                id,
+                setting.setting_type,
+                json_type_name(value)
+            ));
         }
+
+        let value = match value {
+            serde_json::Value::String(s) => s.clone(),
+            v => v.to_string(),
+        };
+        if setting.secret && !value.is_empty() {
+            env.secrets.push(value.clone());
+        }
+        env.vars.push((setting_env_name(&setting.name), value));
     }
 
-    println!("{{}}");
+    Ok(env)
+}
+
+/// Replace secret values in script output before it reaches the model
+fn redact_secrets(text: &str, secrets: &[String]) -> String {
+    let mut redacted = text.to_string();
+    for secret in secrets {
+        redacted = redacted.replace(secret.as_str(), "[REDACTED]");
+    }
+    redacted
 }
 
 // ============================================================================
-// Tool Handlers
+// Script Trust (TOFU)
 // ============================================================================
 
-fn handle_marketplace(args: MarketplaceArgs) {
-    let action = args.action.unwrap_or_default();
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
 
-    match action.as_str() {
-        "install" => {
-            let skill_ref = match args.skill_ref {
-                Some(r) => r,
-                None => {
-                    println!("Error: skill_ref required for install");
-                    return;
-                }
-            };
-            handle_install(&skill_ref);
-        }
-        "remove" => {
-            let skill_ref = match args.skill_ref {
-                Some(r) => r,
-                None => {
-                    println!("Error: skill_ref required for remove");
-                    return;
-                }
-            };
-            handle_remove(&skill_ref);
-        }
-        "search" => {
-            let query = args.query.unwrap_or_default();
-            println!(
-                "{}",
-                serde_json::to_string_pretty(&serde_json::json!([{
-                    "message": format!("Marketplace search not yet implemented. Check https://github.com/anthropics/skills for available skills matching '{}'.", query)
-                }]))
-                .unwrap()
-            );
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Trust database key for a script: `<skill>/<path within skill>`
+fn trust_key(skill_name: &str, rel_path: &Path) -> String {
+    format!("{}/{}", skill_name, rel_path.to_string_lossy())
+}
+
+fn load_trust_db() -> HashMap<String, TrustEntry> {
+    fs::read_to_string(trust_file())
+        .ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+fn record_trust(key: &str, sha256: &str) -> Result<(), String> {
+    let lock_path = plugin_dir().join(".trust.lock");
+    let lock_file = fs::File::create(&lock_path)
+        .map_err(|e| format!("Failed to create trust lock file: {}", e))?;
+    lock_file
+        .lock_exclusive()
+        .map_err(|e| format!("Failed to acquire trust lock: {}", e))?;
+
+    // Lock is released when lock_file is dropped
+    let mut db = load_trust_db();
+    db.insert(
+        key.to_string(),
+        TrustEntry {
+            sha256: sha256.to_string(),
+            approved_at: unix_now(),
+        },
+    );
+    let json = serde_json::to_string_pretty(&db).map_err(|e| e.to_string())?;
+    fs::write(trust_file(), json).map_err(|e| format!("Failed to write trust database: {}", e))
+}
+
+/// Set while handling a chibi hook: nobody is there to answer a prompt mid-hook
+static IN_HOOK: AtomicBool = AtomicBool::new(false);
+
+/// Non-interactive mode and hooks never prompt, so unapproved scripts are refused
+fn is_non_interactive() -> bool {
+    IN_HOOK.load(Ordering::Relaxed) || env::var("AGENT_SKILLS_NONINTERACTIVE").is_ok_and(|v| !v.is_empty() && v != "0")
+}
+
+/// Ensure a script was approved by the user at its current content hash
+///
+/// The first run of a script, or a run after its content changed, asks for
+/// confirmation on /dev/tty (stdin carries the tool arguments). Without a
+/// terminal, or in non-interactive mode, unapproved scripts fail closed.
+fn ensure_script_trusted(skill_name: &str, rel_path: &Path, script_path: &Path) -> Result<String, String> {
+    let content = fs::read(script_path).map_err(|e| format!("Error reading script: {}", e))?;
+    let hash = sha256_hex(&content);
+    let key = trust_key(skill_name, rel_path);
+
+    let previous = load_trust_db().remove(&key);
+    if previous.as_ref().is_some_and(|t| t.sha256 == hash) {
+        return Ok(hash);
+    }
+
+    let approve_hint = format!(
+        "Approve it from a terminal with: agent-skills trust {} {}",
+        skill_name,
+        rel_path.display()
+    );
+    if is_non_interactive() {
+        return Err(format!(
+            "Script '{}' of skill '{}' has not been approved (non-interactive mode). {}",
+            rel_path.display(),
+            skill_name,
+            approve_hint
+        ));
+    }
+
+    let tty = match fs::File::open("/dev/tty") {
+        Ok(t) => t,
+        Err(_) => {
+            return Err(format!(
+                "Script '{}' of skill '{}' has not been approved and no terminal is available to confirm. {}",
+                rel_path.display(),
+                skill_name,
+                approve_hint
+            ));
         }
-        "list" => {
-            println!(
-                "{}",
-                serde_json::to_string_pretty(&serde_json::json!([{
-                    "message": "Marketplace listing not yet implemented. Check https://github.com/anthropics/skills for available skills."
-                }]))
-                .unwrap()
-            );
+    };
+
+    let text = String::from_utf8_lossy(&content);
+    let preview_lines: Vec<&str> = text.lines().take(15).collect();
+    let truncated = text.lines().count() > preview_lines.len();
+
+    eprintln!();
+    eprintln!("┌─────────────────────────────────────────────────────────────");
+    eprintln!("│ Tool: run_skill_script");
+    eprintln!("│ Skill: {}", skill_name);
+    eprintln!("│ Script: {}", script_path.display());
+    eprintln!("│ SHA-256: {}", hash);
+    if previous.is_some() {
+        eprintln!("│ NOTE: script changed since it was last approved");
+    }
+    eprintln!("├─ preview ───────────────────────────────────────────────────");
+    for line in &preview_lines {
+        eprintln!("│ {}", line);
+    }
+    if truncated {
+        eprintln!("│ ...");
+    }
+    eprintln!("└─────────────────────────────────────────────────────────────");
+    eprint!("Run this script? [y/N] ");
+
+    let mut answer = String::new();
+    let _ = io::BufReader::new(tty).read_line(&mut answer);
+
+    if !matches!(answer.trim(), "y" | "Y") {
+        return Err(format!(
+            "Script '{}' of skill '{}' was not approved by the user",
+            rel_path.display(),
+            skill_name
+        ));
+    }
+
+    record_trust(&key, &hash)?;
+    Ok(hash)
+}
+
+// ============================================================================
+// Audit Log
+// ============================================================================
+
+impl AuditEntry {
+    fn new(skill: &str, event: &str) -> Self {
+        AuditEntry {
+            timestamp: unix_now(),
+            context: env::var("CHIBI_CONTEXT").unwrap_or_default(),
+            skill: skill.to_string(),
+            event: event.to_string(),
+            tool: None,
+            trigger: None,
+            detail: None,
+            script: None,
+            script_sha256: None,
+            args_hash: None,
+            exit_code: None,
+            duration_ms: None,
         }
-        "list_installed" => {
-            let skills = list_skills();
-            if skills.is_empty() {
-                println!("No skills installed. Use 'install' action to add skills.");
-            } else {
-                let infos: Vec<SkillInfo> = skills
-                    .into_iter()
-                    .map(|s| SkillInfo {
-                        name: s.name,
-                        description: s.description,
-                    })
-                    .collect();
-                println!("{}", serde_json::to_string_pretty(&infos).unwrap());
+    }
+}
+
+/// Hash of tool/script arguments, so the log records what ran without storing payloads
+fn args_hash(args: &serde_json::Value) -> String {
+    sha256_hex(args.to_string().as_bytes())
+}
+
+/// Append an entry to the audit log with proper locking
+fn write_audit(entry: &AuditEntry) -> Result<(), String> {
+    let lock_path = plugin_dir().join(".audit.lock");
+    let lock_file = fs::File::create(&lock_path)
+        .map_err(|e| format!("Failed to create audit lock file: {}", e))?;
+    lock_file
+        .lock_exclusive()
+        .map_err(|e| format!("Failed to acquire audit lock: {}", e))?;
+
+    let mut log = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(audit_file())
+        .map_err(|e| format!("Failed to open audit log: {}", e))?;
+
+    let json = serde_json::to_string(entry)
+        .map_err(|e| format!("Failed to serialize audit entry: {}", e))?;
+    writeln!(log, "{}", json).map_err(|e| format!("Failed to write audit log: {}", e))?;
+
+    // Lock is released when lock_file is dropped
+    Ok(())
+}
+
+/// Record an audit entry; failures go to stderr rather than the tool result
+fn audit(entry: AuditEntry) {
+    if let Err(e) = write_audit(&entry) {
+        eprintln!("[agent-skills] {}", e);
+    }
+}
+
+// ============================================================================
+// Usage Statistics
+// ============================================================================
+
+fn load_stats() -> HashMap<String, SkillStats> {
+    fs::read_to_string(stats_file())
+        .ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+/// Update a skill's counters under a lock, since hooks and tool calls run concurrently
+fn update_stats(update: impl FnOnce(&mut HashMap<String, SkillStats>)) -> Result<(), String> {
+    let lock_path = plugin_dir().join(".stats.lock");
+    let lock_file = fs::File::create(&lock_path)
+        .map_err(|e| format!("Failed to create stats lock file: {}", e))?;
+    lock_file
+        .lock_exclusive()
+        .map_err(|e| format!("Failed to acquire stats lock: {}", e))?;
+
+    let mut stats = load_stats();
+    update(&mut stats);
+    let json = serde_json::to_string_pretty(&stats).map_err(|e| e.to_string())?;
+    fs::write(stats_file(), json).map_err(|e| format!("Failed to write stats: {}", e))?;
+
+    // Lock is released when lock_file is dropped
+    Ok(())
+}
+
+/// Count a use of a skill and mark it as used now
+fn record_usage(skill_id: &str, update: impl FnOnce(&mut SkillStats)) {
+    let result = update_stats(|stats| {
+        let entry = stats.entry(skill_id.to_string()).or_default();
+        update(entry);
+        entry.last_used = Some(unix_now());
+    });
+    if let Err(e) = result {
+        eprintln!("[agent-skills] {}", e);
+    }
+}
+
+fn format_age(seconds: u64) -> String {
+    match seconds {
+        s if s < 60 => "just now".to_string(),
+        s if s < 3600 => format!("{}m ago", s / 60),
+        s if s < 86400 => format!("{}h ago", s / 3600),
+        s => format!("{}d ago", s / 86400),
+    }
+}
+
+/// Run a skill's self-tests, the scripts directly under its `tests/` directory: `test <skill>`
+///
+/// A test passes when its script exits successfully.
+fn handle_test_cli(args: &[String]) -> Result<serde_json::Value, ActionError> {
+    let Some(skill_ref) = args.first() else {
+        return Err(ActionError::new(ErrorKind::Usage, "Usage: agent-skills test <skill>"));
+    };
+    let skill = resolve_skill(skill_ref).map_err(|e| ActionError::new(ErrorKind::NotFound, e))?;
+
+    // Only top-level runnable files are tests; subdirectories hold fixtures
+    let tests: Vec<PathBuf> = skill_files(&skill.dir.join("tests"), Path::new("tests"))
+        .into_iter()
+        .filter(|rel| rel.components().count() == 2)
+        .filter(|rel| {
+            let path = skill.dir.join(rel);
+            is_executable(&path) || extension_interpreter(&path).is_some()
+        })
+        .collect();
+    if tests.is_empty() {
+        say(format!("Skill '{}' has no self-tests (add scripts under tests/).", skill.id()));
+        return Ok(serde_json::json!({ "skill": skill.id(), "passed": [], "failed": [] }));
+    }
+
+    let (mut passed, mut failed) = (Vec::new(), Vec::new());
+    for test in tests {
+        let test = test.to_string_lossy().to_string();
+        match execute_script(&skill, &test, Vec::new(), None) {
+            Ok(output) if output.success => {
+                say(format!("PASS {}", test));
+                passed.push(test);
+            }
+            Ok(output) => {
+                say(format!("FAIL {}", test));
+                for line in output.display().trim_end().lines() {
+                    say(format!("    {}", line));
+                }
+                failed.push(test);
+            }
+            Err(e) => {
+                say(format!("FAIL {}: {}", test, e));
+                failed.push(test);
             }
-        }
-        _ => {
-            println!("Error: Unknown action '{}'", action);
         }
     }
+    say(format!("\n{} passed, {} failed", passed.len(), failed.len()));
+    if !failed.is_empty() {
+        return Err(ActionError::new(
+            ErrorKind::Failed,
+            format!("{} of {} tests failed: {}", failed.len(), passed.len() + failed.len(), failed.join(", ")),
+        ));
+    }
+    Ok(serde_json::json!({ "skill": skill.id(), "passed": passed, "failed": failed }))
 }
 
-fn handle_install(skill_ref: &str) {
-    let skills_dir = skills_dir();
-    let _ = fs::create_dir_all(&skills_dir);
+/// Show usage per installed skill: `stats [--unused]`
+///
+/// Skills unused for `stale_after_days` (counting from install when never
+/// used) are flagged as candidates for removal.
+fn handle_stats_cli(args: &[String]) -> Result<serde_json::Value, ActionError> {
+    let mut args = args.to_vec();
+    let only_unused = take_flag(&mut args, "--unused");
+    if let Some(unknown) = args.first() {
+        return Err(ActionError::new(
+            ErrorKind::Usage,
+            format!("Unknown argument '{}'. Usage: agent-skills stats [--unused]", unknown),
+        ));
+    }
 
-    // Parse skill reference
-    let (repo_url, skill_name) = if skill_ref.starts_with("http") {
-        let name = skill_ref.trim_end_matches('/').rsplit('/').next().unwrap_or(skill_ref);
-        (skill_ref.to_string(), name.to_string())
-    } else if skill_ref.contains('/') {
-        let parts: Vec<&str> = skill_ref.splitn(2, '/').collect();
-        let owner = parts[0];
-        let name = parts[1];
-        (
-            format!("https://github.com/{}/skills", owner),
-            name.to_string(),
-        )
-    } else {
-        println!("Error: Invalid skill reference '{}'. Use 'owner/skill-name' format.", skill_ref);
-        return;
-    };
+    let skills = discover_skills();
+    if skills.is_empty() {
+        say("No skills installed.");
+        return Ok(serde_json::json!([]));
+    }
+
+    let stats = load_stats();
+    let stale_after = load_config().stale_after_days.unwrap_or(30) * 86400;
+    let now = unix_now();
+
+    let mut rows = Vec::new();
+    for skill in &skills {
+        let id = skill.id();
+        let entry = stats.get(&id).cloned().unwrap_or_default();
+        // Skills installed before install records existed fall back to the SKILL.md mtime
+        let installed_at = load_install_record(&skill.dir).map(|r| r.installed_at).unwrap_or_else(|| {
+            fs::metadata(skill.dir.join("SKILL.md"))
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map_or(now, |d| d.as_secs())
+        });
+        let idle_since = entry.last_used.unwrap_or(installed_at);
+        let stale = now.saturating_sub(idle_since) > stale_after;
+        if only_unused && !stale {
+            continue;
+        }
 
-    let target_dir = skills_dir.join(&skill_name);
-    if target_dir.exists() {
+        rows.push(serde_json::json!({
+            "id": id,
+            "invocations": entry.invocations,
+            "script_runs": entry.script_runs,
+            "failures": entry.failures,
+            "last_used": entry.last_used,
+            "stale": stale,
+        }));
+    }
+
+    if rows.is_empty() {
+        say("No unused skills.");
+    }
+    Ok(serde_json::Value::Array(rows))
+}
+
+/// Print the `stats` table
+fn print_stats(rows: &[serde_json::Value]) {
+    if rows.is_empty() {
+        return;
+    }
+    let now = unix_now();
+    println!(
+        "{:<32} {:>8} {:>8} {:>9}  LAST USED",
+        "SKILL", "INVOKED", "SCRIPTS", "FAILURES"
+    );
+    for row in rows {
+        let last_used = row["last_used"]
+            .as_u64()
+            .map_or("never".to_string(), |t| format_age(now.saturating_sub(t)));
         println!(
-            "Skill '{}' is already installed. Remove it first to reinstall.",
-            skill_name
+            "{:<32} {:>8} {:>8} {:>9}  {}{}",
+            row["id"].as_str().unwrap_or_default(),
+            row["invocations"].as_u64().unwrap_or(0),
+            row["script_runs"].as_u64().unwrap_or(0),
+            row["failures"].as_u64().unwrap_or(0),
+            last_used,
+            if row["stale"] == true { "  (unused, candidate for removal)" } else { "" }
         );
-        return;
     }
+}
+
+// ============================================================================
+// Skill State
+// ============================================================================
+
+/// State file for a skill; scripts receive it as `SKILL_STATE_FILE`
+fn skill_state_file(skill_id: &str) -> PathBuf {
+    state_dir().join(format!("{}.json", skill_id.replace('/', "__")))
+}
+
+/// Lock file guarding a skill's state; scripts receive it as `SKILL_STATE_LOCK`
+fn skill_state_lock(skill_id: &str) -> PathBuf {
+    state_dir().join(format!("{}.lock", skill_id.replace('/', "__")))
+}
 
-    // Clone with sparse checkout
-    let temp_dir = skills_dir.join(format!(".tmp_{}", skill_name));
-    if temp_dir.exists() {
-        let _ = fs::remove_dir_all(&temp_dir);
+/// Read and optionally modify a skill's state under its lock
+fn with_skill_state<T>(
+    skill_id: &str,
+    update: impl FnOnce(&mut SkillState) -> T,
+    save: bool,
+) -> Result<T, String> {
+    fs::create_dir_all(state_dir()).map_err(|e| format!("Failed to create state directory: {}", e))?;
+    let lock_file = fs::File::create(skill_state_lock(skill_id))
+        .map_err(|e| format!("Failed to create state lock file: {}", e))?;
+    lock_file
+        .lock_exclusive()
+        .map_err(|e| format!("Failed to acquire state lock: {}", e))?;
+
+    let path = skill_state_file(skill_id);
+    let mut state: SkillState = match fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content)
+            .map_err(|e| format!("Corrupt state file {}: {}", path.display(), e))?,
+        Err(_) => SkillState::default(),
+    };
+    let result = update(&mut state);
+    if save {
+        let json = serde_json::to_string_pretty(&state).map_err(|e| e.to_string())?;
+        fs::write(&path, json).map_err(|e| format!("Failed to write state: {}", e))?;
     }
 
-    let clone_result = Command::new("git")
-        .args([
-            "clone",
-            "--depth",
-            "1",
-            "--filter=blob:none",
-            "--sparse",
-            &repo_url,
-            temp_dir.to_str().unwrap(),
-        ])
-        .output();
+    // Lock is released when lock_file is dropped
+    Ok(result)
+}
 
-    match clone_result {
-        Ok(output) if output.status.success() => {}
-        Ok(output) => {
-            let _ = fs::remove_dir_all(&temp_dir);
-            println!(
-                "Error cloning repository: {}",
-                String::from_utf8_lossy(&output.stderr)
-            );
+fn handle_skill_state(args: SkillStateArgs) {
+    let (Some(skill_name), Some(operation)) = (args.skill, args.operation) else {
+        println!("Error: 'skill' and 'operation' are required");
+        return;
+    };
+    let skill = match resolve_skill(&skill_name) {
+        Ok(s) => s,
+        Err(e) => {
+            println!("Error: {}", e);
             return;
         }
-        Err(e) => {
-            let _ = fs::remove_dir_all(&temp_dir);
-            println!("Error running git: {}", e);
+    };
+    let id = skill.id();
+    if !is_skill_enabled(&id) {
+        println!("Error: Skill '{}' is disabled", id);
+        return;
+    }
+
+    let context = match args.scope.as_deref() {
+        None | Some("skill") => None,
+        Some("context") => Some(env::var("CHIBI_CONTEXT").unwrap_or_default()),
+        Some(other) => {
+            println!("Error: Unknown scope '{}', use 'skill' or 'context'", other);
             return;
         }
+    };
+    let key = args.key.filter(|k| !k.is_empty());
+    let result = match (operation.as_str(), key) {
+        ("list", _) => {
+            with_skill_state(&id, |state| scope_values(state, context.as_deref()).clone(), false)
+                .map(|values| serde_json::to_string_pretty(&values).unwrap())
+        }
+        ("get", Some(key)) => {
+            with_skill_state(&id, |state| scope_values(state, context.as_deref()).remove(&key), false)
+                .and_then(|value| value.ok_or(format!("Key '{}' is not set for skill '{}'", key, id)))
+                .map(|value| serde_json::to_string_pretty(&value).unwrap())
+        }
+        ("set", Some(key)) => match args.value {
+            Some(value) => with_skill_state(
+                &id,
+                |state| {
+                    scope_values(state, context.as_deref()).insert(key.clone(), value);
+                },
+                true,
+            )
+            .map(|_| format!("Set '{}' for skill '{}'.", key, id)),
+            None => Err("'value' is required for set".to_string()),
+        },
+        ("delete", Some(key)) => with_skill_state(
+            &id,
+            |state| {
+                let values = scope_values(state, context.as_deref());
+                let removed = values.remove(&key).is_some();
+                if values.is_empty() {
+                    if let Some(ctx) = &context {
+                        state.contexts.remove(ctx);
+                    }
+                }
+                removed
+            },
+            true,
+        )
+        .map(|removed| {
+            if removed {
+                format!("Deleted '{}' for skill '{}'.", key, id)
+            } else {
+                format!("Key '{}' was not set for skill '{}'.", key, id)
+            }
+        }),
+        ("get" | "set" | "delete", None) => Err(format!("'key' is required for {}", operation)),
+        (other, _) => Err(format!("Unknown operation '{}'", other)),
+    };
+
+    match result {
+        Ok(output) => println!("{}", output),
+        Err(e) => println!("Error: {}", e),
+    }
+}
+
+/// The value map for a scope: the skill's own, or one context's
+fn scope_values<'a>(state: &'a mut SkillState, context: Option<&str>) -> &'a mut HashMap<String, serde_json::Value> {
+    match context {
+        Some(ctx) => state.contexts.entry(ctx.to_string()).or_default(),
+        None => &mut state.values,
+    }
+}
+
+// ============================================================================
+// State Management
+// ============================================================================
+
+/// Set on the chibi process running a `context: fork` skill; its value is the skill id
+const FORKED_SKILL_ENV: &str = "AGENT_SKILLS_FORKED_SKILL";
+
+fn get_active_skill() -> Option<ActiveSkill> {
+    // A forked sub-agent always runs under its skill, whatever the caller has active
+    if let Ok(id) = env::var(FORKED_SKILL_ENV) {
+        let skill = resolve_skill(&id).ok()?;
+        return Some(ActiveSkill {
+            name: id,
+            allowed_tools: skill.allowed_tools,
+            auto_activated: false,
+        });
+    }
+
+    let path = state_file();
+    if !path.exists() {
+        return None;
+    }
+    let content = fs::read_to_string(&path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn set_active_skill(name: &str, allowed_tools: Option<String>, auto_activated: bool) {
+    // The active skill state belongs to the calling context, not a forked sub-agent
+    if env::var(FORKED_SKILL_ENV).is_ok() {
+        return;
     }
+    let state = ActiveSkill {
+        name: name.to_string(),
+        allowed_tools,
+        auto_activated,
+    };
+    if let Ok(json) = serde_json::to_string(&state) {
+        let _ = fs::write(state_file(), json);
+    }
+}
 
-    // Set up sparse checkout
-    let _ = Command::new("git")
-        .args([
-            "-C",
-            temp_dir.to_str().unwrap(),
-            "sparse-checkout",
-            "set",
-            &format!("skills/{}", skill_name),
-        ])
-        .output();
-
-    // Move skill to target location
-    let skill_source = temp_dir.join("skills").join(&skill_name);
-    if skill_source.exists() {
-        match fs::rename(&skill_source, &target_dir) {
-            Ok(_) => {
-                let _ = fs::remove_dir_all(&temp_dir);
-                println!("Successfully installed skill '{}'.", skill_name);
+fn clear_active_skill() {
+    let path = state_file();
+    if path.exists() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+// ============================================================================
+// Allowed Tools Checking
+// ============================================================================
+
+fn is_tool_allowed(tool_name: &str, allowed_tools: &str) -> bool {
+    // Parse allowed-tools string: "Read, Grep, Bash(git:*)"
+    let allowed_list: Vec<&str> = allowed_tools.split(',').map(|s| s.trim()).collect();
+
+    for allowed in allowed_list {
+        if allowed.contains('(') {
+            // Pattern match: Bash(git:*) - for now, allow if base matches
+            let base = allowed.split('(').next().unwrap_or("");
+            if tool_name == base {
+                return true;
             }
-            Err(e) => {
-                let _ = fs::remove_dir_all(&temp_dir);
-                println!("Error moving skill: {}", e);
+        } else if tool_name == allowed {
+            return true;
+        }
+    }
+
+    false
+}
+
+// ============================================================================
+// Typed Tool Arguments
+// ============================================================================
+
+/// Validate tool arguments against a JSON schema
+///
+/// Supports the subset skills need for flat parameter lists: `type`,
+/// `properties`, `required`, `enum`, `items` and `additionalProperties: false`.
+fn validate_args(value: &serde_json::Value, schema: &serde_json::Value, path: &str) -> Result<(), String> {
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            serde_json::Value::String(t) => vec![t.as_str()],
+            serde_json::Value::Array(ts) => ts.iter().filter_map(|t| t.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|t| json_type_matches(value, t)) {
+            return Err(format!(
+                "'{}' must be of type {}, got {}",
+                path,
+                types.join(" or "),
+                json_type_name(value)
+            ));
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(|e| e.as_array()) {
+        if !allowed.contains(value) {
+            return Err(format!(
+                "'{}' must be one of {}",
+                path,
+                serde_json::to_string(allowed).unwrap_or_default()
+            ));
+        }
+    }
+
+    if let Some(obj) = value.as_object() {
+        let properties = schema.get("properties").and_then(|p| p.as_object());
+
+        if let Some(required) = schema.get("required").and_then(|r| r.as_array()) {
+            for key in required.iter().filter_map(|k| k.as_str()) {
+                if !obj.contains_key(key) {
+                    return Err(format!("missing required parameter '{}'", join_path(path, key)));
+                }
             }
         }
+
+        let no_extras = schema.get("additionalProperties") == Some(&serde_json::Value::Bool(false));
+        for (key, item) in obj {
+            match properties.and_then(|p| p.get(key)) {
+                Some(prop_schema) => validate_args(item, prop_schema, &join_path(path, key))?,
+                None if no_extras => {
+                    return Err(format!("unknown parameter '{}'", join_path(path, key)));
+                }
+                None => {}
+            }
+        }
+    }
+
+    if let (Some(items), Some(item_schema)) = (value.as_array(), schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            validate_args(item, item_schema, &format!("{}[{}]", path, i))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn json_type_matches(value: &serde_json::Value, type_name: &str) -> bool {
+    match type_name {
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn json_type_name(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "boolean",
+        serde_json::Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "array",
+        serde_json::Value::Object(_) => "object",
+    }
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
     } else {
-        let _ = fs::remove_dir_all(&temp_dir);
-        println!("Error: Skill '{}' not found in repository.", skill_name);
+        format!("{}.{}", path, key)
     }
 }
 
-fn handle_remove(skill_ref: &str) {
-    let skill_name = if skill_ref.contains('/') {
-        skill_ref.rsplit('/').next().unwrap_or(skill_ref)
+/// Expand `{param}` placeholders in a typed tool's argument templates
+///
+/// A template referencing a parameter the caller didn't supply is dropped
+/// entirely, so optional flags can be written as `"--pages={pages}"`.
+fn expand_arg_templates(
+    templates: &[String],
+    args: &serde_json::Map<String, serde_json::Value>,
+) -> Vec<String> {
+    let mut expanded = Vec::new();
+
+    'templates: for template in templates {
+        let mut out = String::new();
+        let mut rest = template.as_str();
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start..].find('}') else { break };
+            let key = &rest[start + 1..start + len];
+            let value = match args.get(key) {
+                Some(serde_json::Value::String(s)) => s.clone(),
+                Some(serde_json::Value::Null) | None => continue 'templates,
+                Some(v) => v.to_string(),
+            };
+            out.push_str(&rest[..start]);
+            out.push_str(&value);
+            rest = &rest[start + len + 1..];
+        }
+        out.push_str(rest);
+        expanded.push(out);
+    }
+
+    expanded
+}
+
+// ============================================================================
+// Tool Table
+// ============================================================================
+
+/// What a tool name routes to
+#[derive(Clone)]
+enum ToolKind {
+    Marketplace,
+    ReadSkillFile,
+    ReadSkillSection,
+    RunSkillScript,
+    SkillState,
+    CopySkillAsset,
+    /// Invoke a skill and receive its instructions
+    Skill(Skill),
+    /// Run one of a skill's typed tools
+    SkillTool(Skill, SkillTool),
+}
+
+/// A tool agent-skills provides; schema and routing are generated together
+struct ToolEntry {
+    name: String,
+    kind: ToolKind,
+    /// Disabled skills stay routable (to explain why they can't run) but aren't exposed
+    enabled: bool,
+    schema: serde_json::Value,
+}
+
+/// Names of agent-skills' own tools; skill tools may never shadow them
+const BUILTIN_TOOL_NAMES: &[&str] = &[
+    "skill_marketplace",
+    "read_skill_file",
+    "read_skill_section",
+    "run_skill_script",
+    "skill_state",
+    "copy_skill_asset",
+];
+
+/// Hooks agent-skills handles itself
+const AGENT_SKILLS_HOOKS: &[&str] = &[
+    "post_system_prompt",
+    "pre_message",
+    "pre_tool",
+    "post_tool",
+    "on_start",
+];
+
+/// Every hook chibi fires; skills may attach scripts to any of them
+const CHIBI_HOOKS: &[&str] = &[
+    "on_start",
+    "on_end",
+    "pre_message",
+    "post_message",
+    "pre_tool",
+    "post_tool",
+    "pre_clear",
+    "post_clear",
+    "pre_compact",
+    "post_compact",
+    "pre_rolling_compact",
+    "post_rolling_compact",
+    "pre_system_prompt",
+    "post_system_prompt",
+    "pre_send_message",
+    "post_send_message",
+];
+
+/// Reject skill names whose tool would shadow a built-in tool
+fn check_reserved_skill_name(name: &str) -> Result<(), String> {
+    let tool_name = format!("skill_{}", name);
+    if BUILTIN_TOOL_NAMES.contains(&tool_name.as_str()) {
+        return Err(format!(
+            "Skill name '{}' is reserved: {} is a built-in agent-skills tool",
+            name, tool_name
+        ));
+    }
+    Ok(())
+}
+
+fn builtin_tools() -> Vec<ToolEntry> {
+    vec![
+        ToolEntry {
+            name: "skill_marketplace".to_string(),
+            kind: ToolKind::Marketplace,
+            enabled: true,
+            schema: serde_json::json!({
+                "name": "skill_marketplace",
+                "description": "Install, remove, search, or list Agent Skills from the marketplace",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "action": {
+                            "type": "string",
+                            "enum": ["install", "remove", "enable", "disable", "search", "list", "list_installed"],
+                            "description": "Action to perform"
+                        },
+                        "skill_ref": {
+                            "type": "string",
+                            "description": "Skill reference (owner/name, or repository URL#name) for install, or installed skill name / owner/name for remove, enable and disable"
+                        },
+                        "skill_refs": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "More skill references to install together with skill_ref; all are installed or none (optional)"
+                        },
+                        "context": {
+                            "type": "string",
+                            "description": "Only enable/disable the skill in this context (optional)"
+                        },
+                        "force": {
+                            "type": "boolean",
+                            "description": "Remove the skill even if other installed skills require it (optional)"
+                        },
+                        "offline": {
+                            "type": "boolean",
+                            "description": "Install only from the local source cache (optional)"
+                        },
+                        "query": {
+                            "type": "string",
+                            "description": "Search query for search action"
+                        }
+                    },
+                    "required": ["action"]
+                },
+                "hooks": AGENT_SKILLS_HOOKS
+            }),
+        },
+        ToolEntry {
+            name: "read_skill_file".to_string(),
+            kind: ToolKind::ReadSkillFile,
+            enabled: true,
+            schema: serde_json::json!({
+                "name": "read_skill_file",
+                "description": "Read a file from an installed skill's directory (scripts, references, etc.)",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "skill": {
+                            "type": "string",
+                            "description": "Name of the installed skill (owner/name if the name is ambiguous)"
+                        },
+                        "path": {
+                            "type": "string",
+                            "description": "Relative path to the file within the skill directory"
+                        }
+                    },
+                    "required": ["skill", "path"]
+                }
+            }),
+        },
+        ToolEntry {
+            name: "read_skill_section".to_string(),
+            kind: ToolKind::ReadSkillSection,
+            enabled: true,
+            schema: serde_json::json!({
+                "name": "read_skill_section",
+                "description": "Read one section of a skill's instructions by anchor, or an included file by its @path",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "skill": {
+                            "type": "string",
+                            "description": "Name of the installed skill (owner/name if the name is ambiguous)"
+                        },
+                        "anchor": {
+                            "type": "string",
+                            "description": "Section anchor from the skill's contents, or @path of an included file"
+                        }
+                    },
+                    "required": ["skill", "anchor"]
+                }
+            }),
+        },
+        ToolEntry {
+            name: "run_skill_script".to_string(),
+            kind: ToolKind::RunSkillScript,
+            enabled: true,
+            schema: serde_json::json!({
+                "name": "run_skill_script",
+                "description": "Execute a script from an installed skill's directory (e.g., scripts/extract.py)",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "skill": {
+                            "type": "string",
+                            "description": "Name of the installed skill (owner/name if the name is ambiguous)"
+                        },
+                        "script": {
+                            "type": "string",
+                            "description": "Relative path to the script within the skill directory"
+                        },
+                        "args": {
+                            "type": "array",
+                            "items": {"type": "string"},
+                            "description": "Arguments to pass to the script (optional)"
+                        },
+                        "stdin": {
+                            "type": "string",
+                            "description": "Input to pass to the script via stdin (optional)"
+                        }
+                    },
+                    "required": ["skill", "script"]
+                }
+            }),
+        },
+        ToolEntry {
+            name: "skill_state".to_string(),
+            kind: ToolKind::SkillState,
+            enabled: true,
+            schema: serde_json::json!({
+                "name": "skill_state",
+                "description": "Get, set, delete or list values a skill keeps between invocations",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "skill": {
+                            "type": "string",
+                            "description": "Name of the installed skill (owner/name if the name is ambiguous)"
+                        },
+                        "operation": {
+                            "type": "string",
+                            "enum": ["get", "set", "delete", "list"],
+                            "description": "Operation to perform"
+                        },
+                        "key": {
+                            "type": "string",
+                            "description": "Key to get, set or delete"
+                        },
+                        "value": {
+                            "description": "JSON value to store (for set)"
+                        },
+                        "scope": {
+                            "type": "string",
+                            "enum": ["skill", "context"],
+                            "description": "Keep the value for the skill everywhere, or only in the current context (default: skill)"
+                        }
+                    },
+                    "required": ["skill", "operation"]
+                }
+            }),
+        },
+        ToolEntry {
+            name: "copy_skill_asset".to_string(),
+            kind: ToolKind::CopySkillAsset,
+            enabled: true,
+            schema: serde_json::json!({
+                "name": "copy_skill_asset",
+                "description": "Copy a file or directory from a skill's assets/ into the working directory, optionally filling in {{name}} placeholders. Unlike the other skill tools, it must be listed in an active skill's allowed-tools",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "skill": {
+                            "type": "string",
+                            "description": "Name of the installed skill (owner/name if the name is ambiguous)"
+                        },
+                        "path": {
+                            "type": "string",
+                            "description": "File or directory relative to the skill's assets/ directory"
+                        },
+                        "destination": {
+                            "type": "string",
+                            "description": "Destination relative to the working directory (default: the asset's name)"
+                        },
+                        "variables": {
+                            "type": "object",
+                            "additionalProperties": {"type": "string"},
+                            "description": "Values for {{name}} placeholders in text files (optional)"
+                        },
+                        "overwrite": {
+                            "type": "boolean",
+                            "description": "Replace existing files (default: false)"
+                        }
+                    },
+                    "required": ["skill", "path"]
+                }
+            }),
+        },
+    ]
+}
+
+/// Build the complete tool table: built-in tools plus a tool per installed skill
+/// and per typed skill tool
+///
+/// Skill tool names are derived from all installed skills so they stay stable
+/// when skills are toggled.
+fn tool_table() -> Vec<ToolEntry> {
+    let mut table = builtin_tools();
+
+    let skills = list_skills();
+    let toggles = load_toggles();
+    let context = env::var("CHIBI_CONTEXT").unwrap_or_default();
+    for tool_ref in skill_tool_refs(&skills) {
+        // Defensive: reserved names are rejected at index time, but a clamped
+        // name must never take over a built-in either
+        if BUILTIN_TOOL_NAMES.contains(&tool_ref.tool_name.as_str()) {
+            continue;
+        }
+        let enabled = toggles.is_enabled(&tool_ref.skill.id(), &context);
+
+        // Typed tools declared by the skill
+        if let Some(tool) = tool_ref.typed {
+            table.push(ToolEntry {
+                schema: serde_json::json!({
+                    "name": tool_ref.tool_name,
+                    "description": tool.description,
+                    "parameters": tool.parameters
+                }),
+                name: tool_ref.tool_name,
+                kind: ToolKind::SkillTool(tool_ref.skill.clone(), tool.clone()),
+                enabled,
+            });
+            continue;
+        }
+
+        table.push(ToolEntry {
+            schema: serde_json::json!({
+                "name": tool_ref.tool_name,
+                "description": tool_ref.skill.description,
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "arguments": {
+                            "type": "string",
+                            "description": "Arguments to pass to the skill (optional)"
+                        }
+                    }
+                }
+            }),
+            name: tool_ref.tool_name,
+            kind: ToolKind::Skill(tool_ref.skill.clone()),
+            enabled,
+        });
+    }
+
+    table
+}
+
+fn lookup_tool(tool_name: &str) -> Option<ToolEntry> {
+    tool_table().into_iter().find(|t| t.name == tool_name)
+}
+
+/// Explain a tool that isn't in the table, naming the policy if it hides the skill
+fn unknown_tool_reason(tool_name: &str) -> String {
+    let skills = discover_skills();
+    let owner = skill_tool_refs(&skills).into_iter().find(|r| r.tool_name == tool_name);
+    match owner.map(|r| resolve_skill(&r.skill.id())) {
+        Some(Err(e)) => e,
+        _ => format!("Unknown tool '{}'", tool_name),
+    }
+}
+
+/// Skills that are enabled in the current context
+fn enabled_skills() -> Vec<Skill> {
+    tool_table()
+        .into_iter()
+        .filter(|t| t.enabled)
+        .filter_map(|t| match t.kind {
+            ToolKind::Skill(skill) => Some(skill),
+            _ => None,
+        })
+        .collect()
+}
+
+fn generate_schema() -> serde_json::Value {
+    let mut tools: Vec<serde_json::Value> = tool_table()
+        .into_iter()
+        .filter(|t| t.enabled)
+        .map(|t| t.schema)
+        .collect();
+
+    // Register skill hooks too; whether a skill is enabled is checked when they fire
+    let mut hooks: Vec<&str> = AGENT_SKILLS_HOOKS.to_vec();
+    for skill in list_skills() {
+        for hook in skill.hooks.keys() {
+            if let Some(&known) = CHIBI_HOOKS.iter().find(|h| *h == hook) {
+                if !hooks.contains(&known) {
+                    hooks.push(known);
+                }
+            }
+        }
+    }
+    if let Some(marketplace) = tools.iter_mut().find(|t| t["name"] == "skill_marketplace") {
+        marketplace["hooks"] = serde_json::json!(hooks);
+    }
+
+    serde_json::Value::Array(tools)
+}
+
+// ============================================================================
+// Action Results
+// ============================================================================
+
+/// Set by `--json`: the result envelope goes to stdout, progress lines to stderr
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Print a progress or result line for people, out of the way of `--json` output
+fn say(line: impl std::fmt::Display) {
+    if JSON_OUTPUT.load(Ordering::Relaxed) {
+        eprintln!("{}", line);
     } else {
-        skill_ref
-    };
+        println!("{}", line);
+    }
+}
+
+/// Why a marketplace action failed; each class has its own CLI exit code
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ErrorKind {
+    /// Missing or invalid arguments
+    Usage,
+    /// The skill isn't installed, or isn't in its source repository
+    NotFound,
+    /// Already installed, or still required by other skills
+    Conflict,
+    /// Refused by the admin policy or a reserved name
+    Policy,
+    /// Fetching or checking out the source failed, or it holds no valid skill
+    Source,
+    /// Reading or writing local files failed
+    Io,
+    /// The action ran but what it checked failed, e.g. a skill's self-tests
+    Failed,
+}
+
+impl ErrorKind {
+    /// `error.code` in the `--json` envelope
+    fn code(self) -> &'static str {
+        match self {
+            ErrorKind::Usage => "usage",
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::Conflict => "conflict",
+            ErrorKind::Policy => "policy",
+            ErrorKind::Source => "source",
+            ErrorKind::Io => "io",
+            ErrorKind::Failed => "failed",
+        }
+    }
+
+    fn exit_code(self) -> u8 {
+        match self {
+            ErrorKind::Usage => 2,
+            ErrorKind::NotFound => 3,
+            ErrorKind::Conflict => 4,
+            ErrorKind::Policy => 5,
+            ErrorKind::Source => 6,
+            ErrorKind::Io => 7,
+            ErrorKind::Failed => 1,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ActionError {
+    kind: ErrorKind,
+    message: String,
+}
+
+impl ActionError {
+    fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        ActionError {
+            kind,
+            message: message.into(),
+        }
+    }
+}
 
-    let target_dir = skills_dir().join(skill_name);
-    if !target_dir.exists() {
-        println!("Skill '{}' is not installed.", skill_name);
+// ============================================================================
+// Tool Handlers
+// ============================================================================
+
+fn handle_marketplace(args: MarketplaceArgs) {
+    let action = args.action.clone().unwrap_or_default();
+    // Importing links skills from the working directory, which may be an untrusted checkout
+    if action == "import" {
+        println!("Error: import is only available from the command line: agent-skills import");
+        return;
+    }
+    // A manifest is an arbitrary file path, so the model can't name one
+    if args.manifest.is_some() {
+        println!("Error: manifest is only available from the command line: agent-skills install --manifest <file>");
         return;
     }
+    match run_marketplace_action(args) {
+        Ok(data) => print_listing(&action, &data),
+        Err(e) => println!("Error: {}", e.message),
+    }
+}
+
+/// Human output for the listing actions; the others report as they go
+fn print_listing(action: &str, data: &serde_json::Value) {
+    match action {
+        "list_installed" if data.as_array().is_some_and(|a| a.is_empty()) => {
+            println!("No skills installed. Use 'install' action to add skills.")
+        }
+        "search" | "list" | "list_installed" => println!("{}", serde_json::to_string_pretty(data).unwrap()),
+        "audit" => {
+            for entry in data.as_array().into_iter().flatten() {
+                println!("{}", entry);
+            }
+        }
+        "stats" => print_stats(data.as_array().map_or(&[], |rows| rows.as_slice())),
+        _ => {}
+    }
+}
+
+/// Run a marketplace action, returning its result data
+fn run_marketplace_action(args: MarketplaceArgs) -> Result<serde_json::Value, ActionError> {
+    let action = args.action.unwrap_or_default();
+    let skill_ref = args
+        .skill_ref
+        .ok_or_else(|| ActionError::new(ErrorKind::Usage, format!("skill_ref required for {}", action)));
+
+    match action.as_str() {
+        "install" => {
+            let mut refs: Vec<String> = skill_ref.into_iter().chain(args.skill_refs.unwrap_or_default()).collect();
+            if let Some(manifest) = &args.manifest {
+                refs.extend(read_manifest(manifest)?);
+            }
+            if refs.is_empty() {
+                return Err(ActionError::new(
+                    ErrorKind::Usage,
+                    "skill_ref, skill_refs or manifest required for install",
+                ));
+            }
+            handle_install(&refs, args.offline.unwrap_or(false))
+        }
+        "remove" => handle_remove(&skill_ref?, args.force.unwrap_or(false)),
+        "enable" | "disable" => handle_toggle(&skill_ref?, action == "enable", args.context.as_deref()),
+        "import" => handle_import(skill_ref.ok().as_deref(), args.copy.unwrap_or(false)),
+        "search" => {
+            let query = args.query.unwrap_or_default();
+            Ok(serde_json::json!([{
+                "message": format!("Marketplace search not yet implemented. Check https://github.com/anthropics/skills for available skills matching '{}'.", query)
+            }]))
+        }
+        "list" => Ok(serde_json::json!([{
+            "message": "Marketplace listing not yet implemented. Check https://github.com/anthropics/skills for available skills."
+        }])),
+        "list_installed" => {
+            let toggles = load_toggles();
+            let context = env::var("CHIBI_CONTEXT").unwrap_or_default();
+            let infos: Vec<SkillInfo> = list_skills()
+                .into_iter()
+                .map(|s| {
+                    let record = load_install_record(&s.dir);
+                    SkillInfo {
+                        id: s.id(),
+                        enabled: toggles.is_enabled(&s.id(), &context),
+                        source: record.as_ref().map(|r| r.source.clone()),
+                        modified: record.map(|r| !modified_files(&s.dir, &r).is_empty()),
+                        name: s.name,
+                        description: s.description,
+                    }
+                })
+                .collect();
+            Ok(serde_json::to_value(&infos).unwrap())
+        }
+        _ => Err(ActionError::new(ErrorKind::Usage, format!("Unknown action '{}'", action))),
+    }
+}
+
+/// Enable or disable an installed skill, globally or for one context
+fn handle_toggle(skill_ref: &str, enabled: bool, context: Option<&str>) -> Result<serde_json::Value, ActionError> {
+    let id = resolve_skill(skill_ref)
+        .map_err(|e| ActionError::new(ErrorKind::NotFound, e))?
+        .id();
+
+    let mut toggles = load_toggles();
+    toggles.set(&id, enabled, context);
+    save_toggles(&toggles).map_err(|e| ActionError::new(ErrorKind::Io, e))?;
 
-    match fs::remove_dir_all(&target_dir) {
-        Ok(_) => println!("Successfully removed skill '{}'.", skill_name),
-        Err(e) => println!("Error removing skill: {}", e),
+    let verb = if enabled { "Enabled" } else { "Disabled" };
+    match context {
+        Some(ctx) => say(format!("{} skill '{}' in context '{}'.", verb, id, ctx)),
+        None => say(format!("{} skill '{}'.", verb, id)),
     }
+    Ok(serde_json::json!({ "id": id, "enabled": enabled, "context": context }))
 }
 
 fn handle_read_skill_file(args: ReadSkillFileArgs) {
@@ -634,37 +2448,249 @@ fn handle_read_skill_file(args: ReadSkillFileArgs) {
         }
     };
 
-    let skill_dir = skills_dir().join(&skill_name);
-    if !skill_dir.exists() {
-        println!("Error: Skill '{}' not found", skill_name);
+    let skill = match resolve_skill(&skill_name) {
+        Ok(s) => s,
+        Err(e) => {
+            println!("Error: {}", e);
+            return;
+        }
+    };
+    if !is_skill_enabled(&skill.id()) {
+        println!("Error: Skill '{}' is disabled", skill.id());
         return;
     }
 
-    // Security: resolve path and check for traversal
-    let file_path = skill_dir.join(&rel_path);
-    let canonical_skill_dir = match skill_dir.canonicalize() {
-        Ok(p) => p,
-        Err(_) => {
-            println!("Error: Invalid skill directory");
+    match read_skill_path(&skill.dir, &rel_path) {
+        Ok(content) => print!("{}", content),
+        Err(e) => println!("Error: {}", e),
+    }
+}
+
+/// Read a file inside a skill directory, refusing paths that escape it
+fn read_skill_path(skill_dir: &Path, rel_path: &str) -> Result<String, String> {
+    let canonical_skill_dir = skill_dir
+        .canonicalize()
+        .map_err(|_| "Invalid skill directory".to_string())?;
+    let canonical_file_path = skill_dir
+        .join(rel_path)
+        .canonicalize()
+        .map_err(|_| format!("File not found: {}", rel_path))?;
+
+    if !canonical_file_path.starts_with(&canonical_skill_dir) {
+        return Err("Path traversal not allowed".to_string());
+    }
+
+    fs::read_to_string(&canonical_file_path).map_err(|e| format!("Cannot read {}: {}", rel_path, e))
+}
+
+/// Copy a file or directory tree from a skill's `assets/` into the working directory
+///
+/// Nothing is written if any destination file exists, unless `overwrite` is set.
+fn handle_copy_skill_asset(args: CopySkillAssetArgs) {
+    let (Some(skill_name), Some(rel_path)) = (args.skill, args.path) else {
+        println!("Error: 'skill' and 'path' are required");
+        return;
+    };
+    let skill = match resolve_skill(&skill_name) {
+        Ok(s) => s,
+        Err(e) => {
+            println!("Error: {}", e);
             return;
         }
     };
-    let canonical_file_path = match file_path.canonicalize() {
-        Ok(p) => p,
-        Err(_) => {
-            println!("Error: File not found: {}", rel_path);
+    if !is_skill_enabled(&skill.id()) {
+        println!("Error: Skill '{}' is disabled", skill.id());
+        return;
+    }
+
+    // Security: the source must stay inside assets/, the destination inside the working directory
+    let Ok(assets_dir) = skill.dir.join("assets").canonicalize() else {
+        println!("Error: Skill '{}' has no assets directory", skill.id());
+        return;
+    };
+    let Ok(source) = assets_dir.join(&rel_path).canonicalize() else {
+        println!("Error: Asset not found: {}", rel_path);
+        return;
+    };
+    if !source.starts_with(&assets_dir) {
+        println!("Error: Path traversal not allowed");
+        return;
+    }
+    let destination = match args.destination.filter(|d| !d.is_empty()) {
+        Some(d) => PathBuf::from(d),
+        None => PathBuf::from(source.file_name().unwrap_or_default()),
+    };
+    if destination.is_absolute()
+        || destination
+            .components()
+            .any(|c| matches!(c, std::path::Component::ParentDir))
+    {
+        println!("Error: Destination must be a relative path inside the working directory");
+        return;
+    }
+
+    let files: Vec<(PathBuf, PathBuf)> = if source.is_dir() {
+        skill_files(&source, Path::new(""))
+            .into_iter()
+            .map(|rel| (source.join(&rel), destination.join(&rel)))
+            .collect()
+    } else {
+        vec![(source.clone(), destination.clone())]
+    };
+
+    // Symlinks inside assets/ must not pull in files from elsewhere, and existing
+    // symlinks in the working directory must not redirect the writes
+    let Ok(cwd) = env::current_dir().and_then(|d| d.canonicalize()) else {
+        println!("Error: Cannot resolve the working directory");
+        return;
+    };
+    for (from, to) in &files {
+        if !from.canonicalize().is_ok_and(|f| f.starts_with(&assets_dir)) {
+            println!("Error: Asset {} points outside the assets directory", from.display());
+            return;
+        }
+        if let Err(e) = check_asset_destination(to, &cwd) {
+            println!("Error: {}", e);
+            return;
+        }
+    }
+
+    if !args.overwrite.unwrap_or(false) {
+        let existing: Vec<String> = files
+            .iter()
+            .filter(|(_, to)| to.exists())
+            .map(|(_, to)| to.display().to_string())
+            .collect();
+        if !existing.is_empty() {
+            println!(
+                "Error: Would overwrite existing files: {}. Set overwrite to replace them.",
+                existing.join(", ")
+            );
+            return;
+        }
+    }
+
+    let variables = args.variables.unwrap_or_default();
+    for (from, to) in &files {
+        if let Err(e) = copy_asset_file(from, to, &variables) {
+            println!("Error copying {}: {}", to.display(), e);
+            return;
+        }
+    }
+
+    println!(
+        "Copied {} file(s) from skill '{}' assets/{}:",
+        files.len(),
+        skill.id(),
+        rel_path.trim_start_matches("./")
+    );
+    for (_, to) in &files {
+        println!("- {}", to.display());
+    }
+}
+
+/// Refuse a destination that is, or lies under, an existing symlink, or that resolves outside `cwd`
+fn check_asset_destination(to: &Path, cwd: &Path) -> Result<(), String> {
+    let mut path = PathBuf::new();
+    let mut deepest_existing = cwd.to_path_buf();
+    for component in to.components() {
+        path.push(component);
+        match fs::symlink_metadata(&path) {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                return Err(format!("Destination {} goes through a symlink", path.display()));
+            }
+            Ok(_) => deepest_existing = cwd.join(&path),
+            Err(_) => break,
+        }
+    }
+    let resolved = deepest_existing.canonicalize().map_err(|e| e.to_string())?;
+    if !resolved.starts_with(cwd) {
+        return Err(format!("Destination {} is outside the working directory", to.display()));
+    }
+    Ok(())
+}
+
+/// Files under a skill directory, relative to it, skipping hidden entries
+fn skill_files(dir: &Path, prefix: &Path) -> Vec<PathBuf> {
+    let mut entries: Vec<fs::DirEntry> = fs::read_dir(dir)
+        .map(|e| e.flatten().collect())
+        .unwrap_or_default();
+    entries.sort_by_key(|e| e.file_name());
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let rel = prefix.join(&name);
+        if entry.path().is_dir() {
+            files.extend(skill_files(&entry.path(), &rel));
+        } else {
+            files.push(rel);
+        }
+    }
+    files
+}
+
+/// Copy one asset, filling in `{{name}}` placeholders if it is UTF-8 text
+fn copy_asset_file(from: &Path, to: &Path, variables: &HashMap<String, String>) -> Result<(), String> {
+    if let Some(parent) = to.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let bytes = fs::read(from).map_err(|e| e.to_string())?;
+    let content = match std::str::from_utf8(&bytes) {
+        Ok(text) if !variables.is_empty() && !text.contains('\0') => variables
+            .iter()
+            .fold(text.to_string(), |text, (name, value)| {
+                text.replace(&format!("{{{{{}}}}}", name), value)
+            })
+            .into_bytes(),
+        _ => bytes,
+    };
+    fs::write(to, content).map_err(|e| e.to_string())
+}
+
+/// Print one heading section of a skill's body, or an `@path` include
+fn handle_read_skill_section(args: ReadSkillSectionArgs) {
+    let (Some(skill_name), Some(anchor)) = (args.skill, args.anchor) else {
+        println!("Error: 'skill' and 'anchor' are required");
+        return;
+    };
+    let skill = match resolve_skill(&skill_name) {
+        Ok(s) => s,
+        Err(e) => {
+            println!("Error: {}", e);
             return;
         }
     };
+    if !is_skill_enabled(&skill.id()) {
+        println!("Error: Skill '{}' is disabled", skill.id());
+        return;
+    }
 
-    if !canonical_file_path.starts_with(&canonical_skill_dir) {
-        println!("Error: Path traversal not allowed");
+    if let Some(rel_path) = anchor.strip_prefix('@') {
+        match read_skill_path(&skill.dir, rel_path) {
+            Ok(content) => print!("{}", content),
+            Err(e) => println!("Error: {}", e),
+        }
         return;
     }
 
-    match fs::read_to_string(&canonical_file_path) {
-        Ok(content) => print!("{}", content),
-        Err(e) => println!("Error reading file: {}", e),
+    let anchor = anchor.trim_start_matches('#');
+    let lines: Vec<&str> = skill.body.lines().collect();
+    let sections = body_sections(&lines);
+    match sections.iter().find(|s| s.anchor == anchor) {
+        Some(section) => println!(
+            "{}",
+            defer_includes(&lines[section.start..section.end], &skill.id()).trim_end()
+        ),
+        None => println!(
+            "Error: Skill '{}' has no section '{}'. Available: {}",
+            skill.id(),
+            anchor,
+            sections.iter().map(|s| s.anchor.as_str()).collect::<Vec<_>>().join(", ")
+        ),
     }
 }
 
@@ -686,93 +2712,171 @@ fn handle_run_skill_script(args: RunSkillScriptArgs) {
     };
 
     let script_args = args.args.unwrap_or_default();
-    let stdin_input = args.stdin;
 
-    let skill_dir = skills_dir().join(&skill_name);
-    if !skill_dir.exists() {
-        println!("Error: Skill '{}' not found", skill_name);
+    let skill = match resolve_skill(&skill_name) {
+        Ok(s) => s,
+        Err(e) => {
+            println!("Error: {}", e);
+            return;
+        }
+    };
+    if !is_skill_enabled(&skill.id()) {
+        println!("Error: Skill '{}' is disabled", skill.id());
         return;
     }
 
+    run_script(&skill, &script_path, script_args, args.stdin);
+}
+
+/// Execute a script inside a skill directory and print its output
+fn run_script(
+    skill: &Skill,
+    script_path: &str,
+    script_args: Vec<String>,
+    stdin_input: Option<String>,
+) {
+    match execute_script(skill, script_path, script_args, stdin_input) {
+        Ok(output) => println!("{}", output.display()),
+        Err(e) => println!("Error: {}", e),
+    }
+}
+
+/// Captured result of a skill script, with secrets already redacted
+struct ScriptOutput {
+    stdout: String,
+    stderr: String,
+    exit_code: Option<i32>,
+    success: bool,
+}
+
+impl ScriptOutput {
+    /// Output as shown to the model: stdout, then stderr and a failing exit code
+    fn display(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.stdout.is_empty() {
+            parts.push(self.stdout.clone());
+        }
+        if !self.stderr.is_empty() {
+            parts.push(format!("[stderr]\n{}", self.stderr));
+        }
+        if !self.success {
+            parts.push(format!("[exit code: {}]", self.exit_code.unwrap_or(-1)));
+        }
+        if parts.is_empty() {
+            "(no output)".to_string()
+        } else {
+            parts.join("\n")
+        }
+    }
+}
+
+/// Execute a script inside a skill directory and capture its output
+///
+/// The script runs with the skill's resolved settings as environment variables,
+/// plus `SKILL_DIR`, `SKILL_NAME`, `CHIBI_CONTEXT`, and `SKILL_STATE_FILE` /
+/// `SKILL_STATE_LOCK` for the skill's `skill_state` store (lock before writing).
+fn execute_script(
+    skill: &Skill,
+    script_path: &str,
+    script_args: Vec<String>,
+    stdin_input: Option<String>,
+) -> Result<ScriptOutput, String> {
+    let skill_env = resolve_skill_env(skill)?;
+
     // Security: resolve path and check for traversal
-    let full_path = skill_dir.join(&script_path);
-    let canonical_skill_dir = match skill_dir.canonicalize() {
+    let full_path = skill.dir.join(script_path);
+    let canonical_skill_dir = match skill.dir.canonicalize() {
         Ok(p) => p,
         Err(_) => {
-            println!("Error: Invalid skill directory");
-            return;
+            return Err("Invalid skill directory".to_string());
         }
     };
     let canonical_script_path = match full_path.canonicalize() {
         Ok(p) => p,
         Err(_) => {
-            println!("Error: Script not found: {}", script_path);
-            return;
+            return Err(format!("Script not found: {}", script_path));
+        }
+    };
+
+    if !canonical_script_path.starts_with(&canonical_skill_dir) {
+        return Err("Path traversal not allowed".to_string());
+    }
+
+    let policy = load_policy()?;
+    if let (Some(max), Ok(meta)) = (policy.max_script_bytes, fs::metadata(&canonical_script_path)) {
+        if meta.len() > max {
+            return Err(format!(
+                "Script '{}' is {} bytes, over the policy limit of {} bytes",
+                script_path,
+                meta.len(),
+                max
+            ));
+        }
+    }
+
+    let rel_script_path = canonical_script_path
+        .strip_prefix(&canonical_skill_dir)
+        .unwrap_or(&canonical_script_path);
+    let call_hash = args_hash(&serde_json::json!({"args": script_args, "stdin": stdin_input}));
+    let script_hash = match ensure_script_trusted(&skill.id(), rel_script_path, &canonical_script_path) {
+        Ok(h) => h,
+        Err(e) => {
+            audit(AuditEntry {
+                script: Some(rel_script_path.to_string_lossy().to_string()),
+                args_hash: Some(call_hash),
+                ..AuditEntry::new(&skill.id(), "denied")
+            });
+            return Err(e);
         }
     };
-
-    if !canonical_script_path.starts_with(&canonical_skill_dir) {
-        println!("Error: Path traversal not allowed");
-        return;
-    }
+    let script_audit = |exit_code: Option<i32>, duration_ms: u64| AuditEntry {
+        script: Some(rel_script_path.to_string_lossy().to_string()),
+        script_sha256: Some(script_hash.clone()),
+        args_hash: Some(call_hash.clone()),
+        exit_code,
+        duration_ms: Some(duration_ms),
+        ..AuditEntry::new(&skill.id(), "script")
+    };
 
     // Determine how to run the script
-    let is_executable = std::fs::metadata(&canonical_script_path)
-        .map(|m| {
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::PermissionsExt;
-                m.permissions().mode() & 0o111 != 0
-            }
-            #[cfg(not(unix))]
-            {
-                true
-            }
-        })
-        .unwrap_or(false);
-
-    let (program, cmd_args): (String, Vec<String>) = if is_executable {
-        (
-            canonical_script_path.to_string_lossy().to_string(),
-            script_args,
-        )
-    } else {
-        let ext = canonical_script_path
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("");
-
-        match ext {
-            "py" => {
-                let mut args = vec![canonical_script_path.to_string_lossy().to_string()];
-                args.extend(script_args);
-                ("python3".to_string(), args)
-            }
-            "sh" => {
-                let mut args = vec![canonical_script_path.to_string_lossy().to_string()];
-                args.extend(script_args);
-                ("bash".to_string(), args)
-            }
-            "js" => {
-                let mut args = vec![canonical_script_path.to_string_lossy().to_string()];
-                args.extend(script_args);
-                ("node".to_string(), args)
-            }
-            _ => (
-                canonical_script_path.to_string_lossy().to_string(),
-                script_args,
-            ),
+    let script_path = canonical_script_path.to_string_lossy().to_string();
+    let (program, cmd_args): (String, Vec<String>) = match script_interpreter(&canonical_script_path) {
+        Some(interpreter) => {
+            let mut args = vec![script_path];
+            args.extend(script_args);
+            (interpreter.to_string(), args)
         }
+        None => (script_path, script_args),
     };
 
+    // Scripts may lock or read their state before skill_state was ever used
+    let _ = fs::create_dir_all(state_dir());
+
     let mut cmd = Command::new(&program);
-    cmd.args(&cmd_args).current_dir(&canonical_skill_dir);
+    cmd.args(&cmd_args)
+        .current_dir(&canonical_skill_dir)
+        .envs(skill_env.vars.iter().map(|(k, v)| (k, v)))
+        .env("SKILL_DIR", &canonical_skill_dir)
+        .env("SKILL_NAME", &skill.name)
+        .env("CHIBI_CONTEXT", env::var("CHIBI_CONTEXT").unwrap_or_default())
+        .env("SKILL_STATE_FILE", skill_state_file(&skill.id()))
+        .env("SKILL_STATE_LOCK", skill_state_lock(&skill.id()));
 
     // Handle stdin
     if stdin_input.is_some() {
         cmd.stdin(std::process::Stdio::piped());
     }
 
+    let script_stats = |failed: bool| {
+        record_usage(&skill.id(), |s| {
+            s.script_runs += 1;
+            if failed {
+                s.failures += 1;
+            }
+        })
+    };
+
+    let started = Instant::now();
     let mut child = match cmd
         .stdout(std::process::Stdio::piped())
         .stderr(std::process::Stdio::piped())
@@ -780,8 +2884,9 @@ fn handle_run_skill_script(args: RunSkillScriptArgs) {
     {
         Ok(c) => c,
         Err(e) => {
-            println!("Error executing script: {}", e);
-            return;
+            audit(script_audit(None, 0));
+            script_stats(true);
+            return Err(format!("Failed to execute script: {}", e));
         }
     };
 
@@ -794,80 +2899,413 @@ fn handle_run_skill_script(args: RunSkillScriptArgs) {
 
     // Wait for completion with timeout (2 minutes)
     // Note: Rust's std doesn't have built-in timeout, so we just wait
-    match child.wait_with_output() {
+    let result = child.wait_with_output();
+    let duration_ms = started.elapsed().as_millis() as u64;
+    match result {
         Ok(output) => {
-            let mut parts = Vec::new();
-            if !output.stdout.is_empty() {
-                parts.push(String::from_utf8_lossy(&output.stdout).to_string());
-            }
-            if !output.stderr.is_empty() {
-                parts.push(format!(
-                    "[stderr]\n{}",
-                    String::from_utf8_lossy(&output.stderr)
-                ));
-            }
-            if !output.status.success() {
-                parts.push(format!(
-                    "[exit code: {}]",
-                    output.status.code().unwrap_or(-1)
-                ));
-            }
-            if parts.is_empty() {
-                println!("(no output)");
-            } else {
-                println!("{}", parts.join("\n"));
-            }
+            audit(script_audit(output.status.code(), duration_ms));
+            script_stats(!output.status.success());
+            let redact = |bytes: &[u8]| redact_secrets(&String::from_utf8_lossy(bytes), &skill_env.secrets);
+            Ok(ScriptOutput {
+                stdout: redact(&output.stdout),
+                stderr: redact(&output.stderr),
+                exit_code: output.status.code(),
+                success: output.status.success(),
+            })
         }
         Err(e) => {
-            println!("Error executing script: {}", e);
+            audit(script_audit(None, duration_ms));
+            script_stats(true);
+            Err(format!("Failed to execute script: {}", e))
         }
     }
 }
 
-fn handle_skill_invocation(tool_name: &str, args: SkillInvocationArgs) {
-    if !tool_name.starts_with("skill_") {
-        println!("Error: Invalid skill tool name");
+fn handle_skill_tool(tool_name: &str, skill: &Skill, tool: &SkillTool, args_value: serde_json::Value) {
+    if !is_skill_enabled(&skill.id()) {
+        println!("Error: Skill '{}' is disabled", skill.id());
         return;
     }
 
-    let skill_name = &tool_name[6..];
-    let skill_path = skills_dir().join(skill_name).join("SKILL.md");
+    let args_value = if args_value.is_null() {
+        serde_json::json!({})
+    } else {
+        args_value
+    };
+    if let Err(e) = validate_args(&args_value, &tool.parameters, "") {
+        println!("Error: Invalid arguments for {}: {}", tool_name, e);
+        return;
+    }
 
-    let skill = match parse_skill(&skill_path) {
-        Some(s) => s,
-        None => {
-            println!("Error: Skill '{}' not found or invalid", skill_name);
-            return;
-        }
+    let script_args = match args_value.as_object() {
+        Some(obj) => expand_arg_templates(&tool.args, obj),
+        None => Vec::new(),
     };
 
-    // Build response
-    let mut response = format!("# Skill: {}\n\n{}", skill.name, skill.body);
+    // Scripts also get the validated arguments as JSON on stdin, like chibi plugins
+    run_script(skill, &tool.script, script_args, Some(args_value.to_string()));
+}
 
-    // Include arguments if provided
-    if let Some(arguments) = args.arguments {
-        if !arguments.is_empty() {
-            response.push_str(&format!("\n\n## Arguments\n{}", arguments));
+fn handle_skill_invocation(skill: &Skill, args: SkillInvocationArgs) {
+    if !is_skill_enabled(&skill.id()) {
+        println!("Error: Skill '{}' is disabled", skill.id());
+        return;
+    }
+    record_usage(&skill.id(), |s| s.invocations += 1);
+
+    let arguments = args.arguments.filter(|a| !a.is_empty());
+    if skill.fork_context {
+        match run_forked_skill(skill, arguments.as_deref()) {
+            Ok(answer) => println!("{}", answer),
+            Err(e) => {
+                record_usage(&skill.id(), |s| s.failures += 1);
+                println!("Error: {}", e);
+            }
         }
+        return;
+    }
+
+    let mut response = skill_instructions(skill);
+
+    // Include arguments if provided
+    if let Some(arguments) = arguments {
+        response.push_str(&format!("\n\n## Arguments\n{}", arguments));
     }
 
+    println!("{}", response);
+}
+
+/// Skill body plus notes on supporting files and settings
+fn skill_instructions(skill: &Skill) -> String {
+    let body = if uses_section_disclosure(skill) {
+        sectioned_body(skill)
+    } else {
+        skill.body.clone()
+    };
+    let mut response = format!("# Skill: {}\n\n{}", skill.name, body);
+
     // Check for supporting directories
-    let skill_dir = skills_dir().join(skill_name);
     let supporting_dirs = ["scripts", "references", "assets"];
     let existing_dirs: Vec<&str> = supporting_dirs
         .iter()
-        .filter(|d| skill_dir.join(d).exists())
+        .filter(|d| skill.dir.join(d).exists())
         .copied()
         .collect();
 
     if !existing_dirs.is_empty() {
         response.push_str(&format!(
-            "\n\n## Supporting Files\nThis skill has supporting files in: {}\n- Use `read_skill_file` to read file contents\n- Use `run_skill_script` to execute scripts",
-            existing_dirs.join(", ")
+            "\n\n## Supporting Files\nThis skill has supporting files in: {}\n- Use `read_skill_file` with skill=\"{}\" to read file contents\n- Use `run_skill_script` with skill=\"{}\" to execute scripts",
+            existing_dirs.join(", "),
+            skill.id(),
+            skill.id()
+        ));
+        if existing_dirs.contains(&"assets") {
+            response.push_str(&format!(
+                "\n- Use `copy_skill_asset` with skill=\"{}\" to copy templates from assets/ into the working directory",
+                skill.id()
+            ));
+        }
+    }
+
+    if !skill.settings.is_empty() {
+        response.push_str(&format!("\n\n## Settings\n{}", describe_settings(skill)));
+    }
+
+    let missing = missing_required_tools(skill);
+    if !missing.is_empty() {
+        response.push_str(&format!(
+            "\n\n## Missing Tools\nWarning: this skill requires tools that no installed chibi plugin appears to provide: {}. If they aren't available to you, tell the user before relying on them.",
+            missing.join(", ")
         ));
     }
 
-    println!("{}", response);
+    response
+}
+
+/// A heading section of a SKILL.md body, as line ranges
+///
+/// A section runs until the next heading of the same or a higher level, so
+/// it includes its subsections.
+struct BodySection {
+    level: usize,
+    title: String,
+    anchor: String,
+    start: usize,
+    end: usize,
+}
+
+fn uses_section_disclosure(skill: &Skill) -> bool {
+    let mode = skill.disclosure.clone().or_else(|| load_config().disclosure);
+    mode.as_deref() == Some("sections")
+}
+
+/// Split a body into heading sections, ignoring `#` lines inside code fences
+fn body_sections(lines: &[&str]) -> Vec<BodySection> {
+    let mut sections: Vec<BodySection> = Vec::new();
+    let mut in_fence = false;
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        let level = line.chars().take_while(|c| *c == '#').count();
+        if in_fence || level == 0 || level > 6 || !line[level..].starts_with(' ') {
+            continue;
+        }
+
+        let title = line[level..].trim().to_string();
+        let slug = heading_anchor(&title);
+        let count = seen.entry(slug.clone()).or_insert(0);
+        let anchor = if *count == 0 { slug.clone() } else { format!("{}-{}", slug, count) };
+        *count += 1;
+
+        for open in sections.iter_mut().filter(|s| s.end == lines.len() && s.level >= level) {
+            open.end = i;
+        }
+        sections.push(BodySection {
+            level,
+            title,
+            anchor,
+            start: i,
+            end: lines.len(),
+        });
+    }
+
+    sections
+}
+
+/// GitHub-style heading anchor: lowercase, spaces to hyphens, punctuation dropped
+fn heading_anchor(title: &str) -> String {
+    title
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// `@path` on a line of its own includes a skill file
+fn include_directive(line: &str) -> Option<&str> {
+    let path = line.trim().strip_prefix('@')?;
+    (!path.is_empty() && !path.contains(char::is_whitespace) && path.contains(['/', '.'])).then_some(path)
+}
+
+/// Replace include directives with a pointer to read the file on demand
+fn defer_includes(lines: &[&str], skill_id: &str) -> String {
+    lines
+        .iter()
+        .map(|line| match include_directive(line) {
+            Some(path) => format!(
+                "[Included file `{}`: read it with read_skill_section skill=\"{}\" anchor=\"@{}\" when needed]",
+                path, skill_id, path
+            ),
+            None => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The body's first section plus a table of contents for the rest
+fn sectioned_body(skill: &Skill) -> String {
+    let lines: Vec<&str> = skill.body.lines().collect();
+    let sections = body_sections(&lines);
+
+    // The first section is everything up to the second heading, so a leading
+    // title heading doesn't leave the first section empty
+    let first_content = lines.iter().position(|l| !l.trim().is_empty()).unwrap_or(0);
+    let Some(first_end) = sections.iter().map(|s| s.start).find(|&start| start > first_content) else {
+        return skill.body.clone();
+    };
+
+    let rest: Vec<&BodySection> = sections.iter().filter(|s| s.start >= first_end).collect();
+    let top_level = rest.iter().map(|s| s.level).min().unwrap_or(1);
+    let contents: Vec<String> = rest
+        .iter()
+        .map(|s| format!("{}- `{}` {}", "  ".repeat(s.level - top_level), s.anchor, s.title))
+        .collect();
+
+    format!(
+        "{}\n\n## Contents\nThe rest of this skill is split into sections. Use `read_skill_section` with skill=\"{}\" and an anchor below to read a section when it becomes relevant.\n{}",
+        defer_includes(&lines[..first_end], &skill.id()).trim_end(),
+        skill.id(),
+        contents.join("\n")
+    )
+}
+
+/// Tools from `requires-tools` that no installed chibi plugin appears to provide
+///
+/// chibi doesn't tell plugins which tools it has, and asking other plugins for
+/// their `--schema` would run them, so plugins are matched by file name only
+/// (`web-search` also provides `web_search`). The result is only a warning.
+fn missing_required_tools(skill: &Skill) -> Vec<String> {
+    if skill.requires_tools.is_empty() {
+        return Vec::new();
+    }
+
+    let mut available: Vec<String> = tool_table().into_iter().map(|t| t.name).collect();
+    let plugins = fs::read_dir(plugin_dir())
+        .map(|entries| entries.flatten().map(|e| e.path()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    for plugin in plugins.iter().filter(|p| p.is_file() && is_executable(p)) {
+        if let Some(name) = plugin.file_name().map(|n| n.to_string_lossy().to_string()) {
+            available.push(name.replace('-', "_"));
+            available.push(name);
+        }
+    }
+
+    skill
+        .requires_tools
+        .iter()
+        .filter(|t| !available.contains(t))
+        .cloned()
+        .collect()
+}
+
+/// Interpreter execute_script uses for a non-executable script, by extension
+fn extension_interpreter(path: &Path) -> Option<&'static str> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("py") => Some("python3"),
+        Some("sh") => Some("bash"),
+        Some("js") => Some("node"),
+        _ => None,
+    }
+}
+
+/// Interpreter execute_script runs a script with; `None` means it's run directly
+fn script_interpreter(path: &Path) -> Option<&'static str> {
+    if is_executable(path) {
+        None
+    } else {
+        extension_interpreter(path)
+    }
+}
+
+/// Whether a file has an execute bit set (always true off Unix)
+fn is_executable(path: &Path) -> bool {
+    fs::metadata(path)
+        .map(|m| {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                m.permissions().mode() & 0o111 != 0
+            }
+            #[cfg(not(unix))]
+            {
+                true
+            }
+        })
+        .unwrap_or(false)
+}
+
+/// Run a `context: fork` skill as a chibi sub-agent and return its final answer
+fn run_forked_skill(skill: &Skill, arguments: Option<&str>) -> Result<String, String> {
+    let chibi = load_config().chibi_path.unwrap_or_else(|| "chibi".to_string());
+    run_in_sub_context(&chibi, &chibi_dir()?.join("contexts"), skill, arguments)
+}
+
+/// Run `chibi -S` on a new context under `contexts_dir`
+///
+/// The sub-context gets the skill instructions as its system prompt and the
+/// arguments as the prompt, so the caller's context only sees the result.
+/// chibi owns the context once it has run, so it's left in place for chibi
+/// to manage; the `skill-` prefix makes these contexts easy to find.
+fn run_in_sub_context(chibi: &str, contexts_dir: &Path, skill: &Skill, arguments: Option<&str>) -> Result<String, String> {
+    let context = format!("skill-{}-{}-{}", skill.name, unix_now(), std::process::id());
+
+    let ctx_dir = contexts_dir.join(&context);
+    fs::create_dir_all(&ctx_dir)
+        .map_err(|e| format!("Failed to create context directory: {}", e))?;
+    let instructions = format!(
+        "{}\n\n## Sub-agent\nYou are running skill '{}' in a dedicated sub-context. Only your final answer is returned to the caller, so finish with a concise summary of the outcome.",
+        skill_instructions(skill),
+        skill.id()
+    );
+    fs::write(ctx_dir.join("system_prompt.md"), instructions)
+        .map_err(|e| format!("Failed to write sub-context system prompt: {}", e))?;
+
+    let prompt = arguments.unwrap_or("Carry out the skill's instructions.");
+
+    // Use -S (sub-context) to run without changing chibi's current context
+    let output = Command::new(chibi)
+        .args(["-S", &context, prompt])
+        .env(FORKED_SKILL_ENV, skill.id())
+        .stdin(std::process::Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to run chibi at {}: {}", chibi, e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Sub-agent for skill '{}' failed ({}): {}",
+            skill.id(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(format!(
+        "# Skill result: {}\n(ran in sub-context {})\n\n{}",
+        skill.id(),
+        context,
+        String::from_utf8_lossy(&output.stdout).trim()
+    ))
+}
+
+/// Summarise a skill's settings for the model without revealing any values
+fn describe_settings(skill: &Skill) -> String {
+    let config = load_config();
+    let secrets = load_secrets();
+    let id = skill.id();
+    let configured = config.settings.get(&id).or_else(|| config.settings.get(&skill.name));
+    let secret_values = match &secrets {
+        Ok(s) => s.get(&id).or_else(|| s.get(&skill.name)),
+        Err(_) => None,
+    };
+
+    let mut lines = Vec::new();
+    for setting in &skill.settings {
+        let is_set = secret_values.is_some_and(|v| v.contains_key(&setting.name))
+            || configured.is_some_and(|v| v.contains_key(&setting.name));
+
+        let status = if is_set {
+            "configured"
+        } else if setting.default.is_some() {
+            "using default"
+        } else if setting.secret {
+            "not set (add it to agent-skills.secrets.json)"
+        } else {
+            "not set (add it to agent-skills.config.json)"
+        };
+
+        let kind = if setting.secret {
+            format!("{}, secret", setting.setting_type)
+        } else {
+            setting.setting_type.clone()
+        };
+        let description = setting
+            .description
+            .as_deref()
+            .map(|d| format!(": {}", d))
+            .unwrap_or_default();
+        lines.push(format!(
+            "- `{}` ({}){} [{}] -> ${}",
+            setting.name,
+            kind,
+            description,
+            status,
+            setting_env_name(&setting.name)
+        ));
+    }
+
+    if let Err(e) = secrets {
+        lines.push(format!("\nWarning: {}", e));
+    }
+    lines.join("\n")
 }
 
 // ============================================================================
@@ -875,50 +3313,50 @@ fn handle_skill_invocation(tool_name: &str, args: SkillInvocationArgs) {
 // ============================================================================
 
 fn handle_tool_call(stdin_data: &str) {
-    let args_str = stdin_data;
     let tool_name = env::var("CHIBI_TOOL_NAME").unwrap_or_default();
+    if tool_name.is_empty() {
+        println!("Error: Cannot determine tool name. Please set CHIBI_TOOL_NAME environment variable.");
+        return;
+    }
 
-    // Parse args as generic JSON first
-    let args_value: serde_json::Value = serde_json::from_str(args_str).unwrap_or_default();
-
-    // Determine tool name if not provided
-    let tool_name = if tool_name.is_empty() {
-        // Try to infer from args structure
-        if args_value.get("action").is_some() {
-            "skill_marketplace".to_string()
-        } else if args_value.get("script").is_some() && args_value.get("skill").is_some() {
-            "run_skill_script".to_string()
-        } else if args_value.get("path").is_some() && args_value.get("skill").is_some() {
-            "read_skill_file".to_string()
-        } else {
-            println!("Error: Cannot determine tool name. Please set CHIBI_TOOL_NAME environment variable.");
-            return;
-        }
-    } else {
-        tool_name
+    let args_value: serde_json::Value = serde_json::from_str(stdin_data).unwrap_or_default();
+
+    // Route through the same table generate_schema uses
+    let Some(entry) = lookup_tool(&tool_name) else {
+        println!("Error: {}", unknown_tool_reason(&tool_name));
+        return;
     };
 
-    // Route to handler
-    match tool_name.as_str() {
-        "skill_marketplace" => {
+    match entry.kind {
+        ToolKind::Marketplace => {
             let args: MarketplaceArgs = serde_json::from_value(args_value).unwrap_or_default();
             handle_marketplace(args);
         }
-        "read_skill_file" => {
+        ToolKind::ReadSkillFile => {
             let args: ReadSkillFileArgs = serde_json::from_value(args_value).unwrap_or_default();
             handle_read_skill_file(args);
         }
-        "run_skill_script" => {
+        ToolKind::ReadSkillSection => {
+            let args: ReadSkillSectionArgs = serde_json::from_value(args_value).unwrap_or_default();
+            handle_read_skill_section(args);
+        }
+        ToolKind::RunSkillScript => {
             let args: RunSkillScriptArgs = serde_json::from_value(args_value).unwrap_or_default();
             handle_run_skill_script(args);
         }
-        name if name.starts_with("skill_") => {
-            let args: SkillInvocationArgs = serde_json::from_value(args_value).unwrap_or_default();
-            handle_skill_invocation(name, args);
+        ToolKind::SkillState => {
+            let args: SkillStateArgs = serde_json::from_value(args_value).unwrap_or_default();
+            handle_skill_state(args);
+        }
+        ToolKind::CopySkillAsset => {
+            let args: CopySkillAssetArgs = serde_json::from_value(args_value).unwrap_or_default();
+            handle_copy_skill_asset(args);
         }
-        _ => {
-            println!("Error: Unknown tool '{}'", tool_name);
+        ToolKind::Skill(skill) => {
+            let args: SkillInvocationArgs = serde_json::from_value(args_value).unwrap_or_default();
+            handle_skill_invocation(&skill, args);
         }
+        ToolKind::SkillTool(skill, tool) => handle_skill_tool(&tool_name, &skill, &tool, args_value),
     }
 }
 
@@ -926,25 +3364,234 @@ fn handle_tool_call(stdin_data: &str) {
 // CLI Mode
 // ============================================================================
 
-fn handle_cli(args: &[String]) {
+/// Run a CLI action; marketplace actions exit non-zero by failure class (see `ErrorKind`)
+fn handle_cli(args: &[String]) -> ExitCode {
+    let mut args = args.to_vec();
+    let context = take_flag_value(&mut args, "--context");
+    let force = take_flag(&mut args, "--force");
+    let offline = take_flag(&mut args, "--offline");
+    let copy = take_flag(&mut args, "--copy");
+    let json = take_flag(&mut args, "--json");
+    let manifest = take_flag_value(&mut args, "--manifest");
+    JSON_OUTPUT.store(json, Ordering::Relaxed);
+
     if args.len() < 2 {
-        println!("Usage: agent-skills <action> [args...]");
-        println!("Actions: install, remove, search, list, list_installed");
-        return;
+        if json {
+            return print_envelope("", Err(ActionError::new(ErrorKind::Usage, "No action given")));
+        }
+        println!("Usage: agent-skills <action> [args...] [--context <name>] [--force] [--offline] [--copy] [--json] [--manifest <file>]");
+        println!("Actions: install, remove, enable, disable, search, list, list_installed, import, trust, audit, mirror, stats, test, doctor, mcp, tui");
+        return ExitCode::from(ErrorKind::Usage.exit_code());
     }
 
     let action = &args[1];
-    let marketplace_args = MarketplaceArgs {
-        action: Some(action.clone()),
-        skill_ref: args.get(2).cloned(),
-        query: if action == "search" {
-            Some(args[2..].join(" "))
-        } else {
-            None
-        },
+    // The terminal UI and the MCP server own the terminal; there is no result to wrap
+    match action.as_str() {
+        "tui" | "mcp" if json => {
+            let message = format!("--json is not supported for '{}'", action);
+            return print_envelope(action, Err(ActionError::new(ErrorKind::Usage, message)));
+        }
+        "tui" => {
+            return match tui::run() {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    print_cli_error(&ActionError::new(ErrorKind::Io, e));
+                    ExitCode::from(ErrorKind::Io.exit_code())
+                }
+            };
+        }
+        "mcp" => {
+            run_mcp_server();
+            return ExitCode::SUCCESS;
+        }
+        _ => {}
+    }
+
+    // Approving scripts is deliberately CLI-only so the model can't trust its own scripts
+    let result = match action.as_str() {
+        "trust" => handle_trust_cli(&args[2..]),
+        "audit" => handle_audit_cli(&args[2..], context),
+        "mirror" => handle_mirror_cli(&args[2..]),
+        "stats" => handle_stats_cli(&args[2..]),
+        "test" => handle_test_cli(&args[2..]),
+        "doctor" => handle_doctor_cli(),
+        _ => run_marketplace_action(MarketplaceArgs {
+            action: Some(action.clone()),
+            skill_ref: args.get(2).cloned(),
+            skill_refs: (action == "install").then(|| args.get(3..).unwrap_or_default().to_vec()),
+            manifest,
+            query: if action == "search" {
+                Some(args[2..].join(" "))
+            } else {
+                None
+            },
+            context,
+            force: Some(force),
+            offline: Some(offline),
+            copy: Some(copy),
+        }),
+    };
+    if json {
+        return print_envelope(action, result);
+    }
+    match result {
+        Ok(data) => {
+            print_listing(action, &data);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            print_cli_error(&e);
+            ExitCode::from(e.kind.exit_code())
+        }
+    }
+}
+
+/// Usage errors print the usage text as is; other errors get an `Error:` prefix
+fn print_cli_error(e: &ActionError) {
+    if e.message.starts_with("Usage:") {
+        println!("{}", e.message);
+    } else {
+        println!("Error: {}", e.message);
+    }
+}
+
+/// Print the `--json` result envelope and pick the exit code
+fn print_envelope(action: &str, result: Result<serde_json::Value, ActionError>) -> ExitCode {
+    let (envelope, code) = match result {
+        Ok(data) => (
+            serde_json::json!({ "ok": true, "action": action, "data": data }),
+            ExitCode::SUCCESS,
+        ),
+        Err(e) => (
+            serde_json::json!({
+                "ok": false,
+                "action": action,
+                "error": { "code": e.kind.code(), "message": e.message },
+            }),
+            ExitCode::from(e.kind.exit_code()),
+        ),
+    };
+    println!("{}", serde_json::to_string_pretty(&envelope).unwrap());
+    code
+}
+
+/// Remove a boolean `--flag` from CLI args, returning whether it was present
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let before = args.len();
+    args.retain(|a| a != flag);
+    args.len() != before
+}
+
+/// Remove `--flag <value>` from CLI args, returning the value
+fn take_flag_value(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    let pos = args.iter().position(|a| a == flag)?;
+    if pos + 1 >= args.len() {
+        args.remove(pos);
+        return None;
+    }
+    let value = args.remove(pos + 1);
+    args.remove(pos);
+    Some(value)
+}
+
+/// Pre-approve a skill script: agent-skills trust <skill> <script>
+fn handle_trust_cli(args: &[String]) -> Result<serde_json::Value, ActionError> {
+    let (skill_ref, script) = match args {
+        [skill, script] => (skill, script),
+        _ => return Err(ActionError::new(ErrorKind::Usage, "Usage: agent-skills trust <skill> <script>")),
+    };
+
+    let skill = resolve_skill(skill_ref).map_err(|e| ActionError::new(ErrorKind::NotFound, e))?;
+    let skill_id = skill.id();
+    let (canonical_skill_dir, canonical_script_path) =
+        match (skill.dir.canonicalize(), skill.dir.join(script).canonicalize()) {
+            (Ok(d), Ok(p)) => (d, p),
+            (Err(_), _) => return Err(ActionError::new(ErrorKind::Io, "Invalid skill directory")),
+            (_, Err(_)) => {
+                return Err(ActionError::new(ErrorKind::NotFound, format!("Script not found: {}", script)));
+            }
+        };
+    if !canonical_script_path.starts_with(&canonical_skill_dir) {
+        return Err(ActionError::new(ErrorKind::Usage, "Path traversal not allowed"));
+    }
+
+    let content = fs::read(&canonical_script_path)
+        .map_err(|e| ActionError::new(ErrorKind::Io, format!("Cannot read script: {}", e)))?;
+    let hash = sha256_hex(&content);
+    let rel_path = canonical_script_path
+        .strip_prefix(&canonical_skill_dir)
+        .unwrap_or(&canonical_script_path);
+
+    record_trust(&trust_key(&skill_id, rel_path), &hash).map_err(|e| ActionError::new(ErrorKind::Io, e))?;
+    say(format!(
+        "Trusted {} of skill '{}' (sha256 {}).",
+        rel_path.display(),
+        skill_id,
+        hash
+    ));
+    Ok(serde_json::json!({ "skill": skill_id, "script": rel_path, "sha256": hash }))
+}
+
+/// Matching audit log entries, printed as JSONL:
+/// agent-skills audit [--skill S] [--event E] [--context C] [--since UNIX_TS] [--limit N]
+///
+/// `--context` is taken out of the args with the other global flags, so it arrives separately.
+fn handle_audit_cli(args: &[String], context: Option<String>) -> Result<serde_json::Value, ActionError> {
+    let mut skill = None;
+    let mut event = None;
+    let mut context = context;
+    let mut since = 0u64;
+    let mut limit = None;
+
+    let usage = "Usage: agent-skills audit [--skill S] [--event E] [--context C] [--since UNIX_TS] [--limit N]";
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        let Some(value) = iter.next() else {
+            return Err(ActionError::new(ErrorKind::Usage, usage));
+        };
+        match flag.as_str() {
+            "--skill" => skill = Some(value.clone()),
+            "--event" => event = Some(value.clone()),
+            "--context" => context = Some(value.clone()),
+            "--since" | "--limit" => {
+                let Ok(n) = value.parse::<u64>() else {
+                    return Err(ActionError::new(
+                        ErrorKind::Usage,
+                        format!("{} expects a number, got '{}'", flag, value),
+                    ));
+                };
+                if flag == "--since" {
+                    since = n;
+                } else {
+                    limit = Some(n as usize);
+                }
+            }
+            _ => return Err(ActionError::new(ErrorKind::Usage, usage)),
+        }
+    }
+
+    let content = match fs::read_to_string(audit_file()) {
+        Ok(c) => c,
+        Err(_) => {
+            say("No audit entries recorded yet.");
+            return Ok(serde_json::json!([]));
+        }
     };
 
-    handle_marketplace(marketplace_args);
+    let mut matching: Vec<AuditEntry> = content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<AuditEntry>(line).ok())
+        .filter(|entry| {
+            entry.timestamp >= since
+                && skill.as_ref().is_none_or(|s| &entry.skill == s)
+                && event.as_ref().is_none_or(|e| &entry.event == e)
+                && context.as_ref().is_none_or(|c| &entry.context == c)
+        })
+        .collect();
+
+    // --limit keeps the most recent entries
+    let start = limit.map_or(0, |n| matching.len().saturating_sub(n));
+    Ok(serde_json::to_value(matching.split_off(start)).unwrap())
 }
 
 // ============================================================================
@@ -963,20 +3610,24 @@ fn main() -> ExitCode {
 
     // Check if we're being called as a hook
     if let Ok(hook) = env::var("CHIBI_HOOK") {
+        IN_HOOK.store(true, Ordering::Relaxed);
         let stdin_data = read_stdin();
-        match hook.as_str() {
+        let own = match hook.as_str() {
             "on_start" => handle_on_start_hook(),
             "post_system_prompt" => handle_post_system_prompt_hook(),
+            "pre_message" => handle_pre_message_hook(&stdin_data),
             "pre_tool" => handle_pre_tool_hook(&stdin_data),
-            _ => println!("{{}}"),
-        }
+            "post_tool" => handle_post_tool_hook(&stdin_data),
+            _ => serde_json::json!({}),
+        };
+        let responses = run_skill_hooks(&hook, &stdin_data, own);
+        println!("{}", merge_hook_responses(responses));
         return ExitCode::SUCCESS;
     }
 
     // CLI mode (must check before tool call since both read stdin)
     if args.len() > 1 {
-        handle_cli(&args);
-        return ExitCode::SUCCESS;
+        return handle_cli(&args);
     }
 
     // No CLI args and not a hook — this is a tool call (args via stdin)
@@ -984,3 +3635,175 @@ fn main() -> ExitCode {
     handle_tool_call(&stdin_data);
     ExitCode::SUCCESS
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+    /// Fresh scratch directory, removed when the returned guard drops
+    pub struct TempDir(pub PathBuf);
+
+    impl TempDir {
+        pub fn new() -> Self {
+            let dir = env::temp_dir().join(format!(
+                "agent-skills-unit-{}-{}",
+                std::process::id(),
+                NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn args(value: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn validate_args_checks_types_required_and_extras() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "file": {"type": "string"},
+                "pages": {"type": "array", "items": {"type": "integer"}},
+                "mode": {"enum": ["fast", "exact"]}
+            },
+            "required": ["file"],
+            "additionalProperties": false
+        });
+        let check = |value: serde_json::Value| validate_args(&value, &schema, "");
+
+        assert!(check(serde_json::json!({"file": "a.pdf", "pages": [1, 2], "mode": "fast"})).is_ok());
+        assert_eq!(check(serde_json::json!({})).unwrap_err(), "missing required parameter 'file'");
+        assert_eq!(
+            check(serde_json::json!({"file": 3})).unwrap_err(),
+            "'file' must be of type string, got integer"
+        );
+        assert_eq!(
+            check(serde_json::json!({"file": "a", "pages": [1, 2.5]})).unwrap_err(),
+            "'pages[1]' must be of type integer, got number"
+        );
+        assert_eq!(
+            check(serde_json::json!({"file": "a", "mode": "slow"})).unwrap_err(),
+            "'mode' must be one of [\"fast\",\"exact\"]"
+        );
+        assert_eq!(check(serde_json::json!({"file": "a", "dpi": 3})).unwrap_err(), "unknown parameter 'dpi'");
+    }
+
+    #[test]
+    fn arg_templates_expand_and_drop_missing_parameters() {
+        let templates: Vec<String> = ["--pages={pages}", "{file}", "--verbose", "{a}-{b}", "{unclosed"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+
+        let expanded = expand_arg_templates(&templates, &args(serde_json::json!({"file": "a b.pdf", "a": 1, "b": true})));
+        assert_eq!(expanded, ["a b.pdf", "--verbose", "1-true", "{unclosed"]);
+
+        let expanded = expand_arg_templates(&templates, &args(serde_json::json!({"pages": "1-3", "file": null})));
+        assert_eq!(expanded, ["--pages=1-3", "--verbose", "{unclosed"]);
+    }
+
+    fn skill(namespace: Option<&str>, name: &str) -> Skill {
+        Skill {
+            namespace: namespace.map(String::from),
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn tool_base_is_qualified_only_for_shared_names() {
+        let skills = [skill(Some("acme"), "pdf"), skill(Some("other"), "pdf"), skill(None, "notes")];
+        assert_eq!(skill_tool_base(&skills[0], &skills), "skill_acme_pdf");
+        assert_eq!(skill_tool_base(&skills[1], &skills), "skill_other_pdf");
+        assert_eq!(skill_tool_base(&skills[2], &skills), "skill_notes");
+        assert_eq!(skill_tool_base(&skills[0], &skills[..1]), "skill_pdf");
+    }
+
+    #[test]
+    fn long_tool_names_are_clamped_and_stay_distinct() {
+        assert_eq!(clamp_tool_name("skill_pdf".to_string(), "acme/pdf"), "skill_pdf");
+
+        let long = format!("skill_{}", "a".repeat(80));
+        let first = clamp_tool_name(long.clone(), "acme/a");
+        let second = clamp_tool_name(long.clone(), "other/a");
+        assert_eq!(first.len(), MAX_TOOL_NAME_LEN);
+        assert_eq!(second.len(), MAX_TOOL_NAME_LEN);
+        assert_ne!(first, second);
+        assert_eq!(clamp_tool_name(long, "acme/a"), first);
+    }
+
+    #[test]
+    fn skill_names_may_not_shadow_builtin_tools() {
+        assert!(check_reserved_skill_name("pdf").is_ok());
+        assert!(check_reserved_skill_name("state-machine").is_ok());
+        assert_eq!(
+            check_reserved_skill_name("marketplace").unwrap_err(),
+            "Skill name 'marketplace' is reserved: skill_marketplace is a built-in agent-skills tool"
+        );
+        assert!(check_reserved_skill_name("state").is_err());
+
+        let tmp = TempDir::new();
+        let skill_md = tmp.0.join("SKILL.md");
+        fs::write(&skill_md, "---\nname: state\ndescription: Shadows skill_state\n---\nBody\n").unwrap();
+        assert!(parse_skill(&skill_md).is_none());
+    }
+
+    #[test]
+    fn body_sections_nest_and_skip_code_fences() {
+        let body = "intro\n# Setup\ntext\n## Install\n```sh\n# not a heading\n```\n## Install\n#NoSpace\n# Usage & Tips\nend";
+        let lines: Vec<&str> = body.lines().collect();
+        let found = body_sections(&lines);
+        let sections: Vec<(usize, &str, &str, usize, usize)> = found
+            .iter()
+            .map(|s| (s.level, s.title.as_str(), s.anchor.as_str(), s.start, s.end))
+            .collect();
+        assert_eq!(
+            sections,
+            [
+                (1, "Setup", "setup", 1, 9),
+                (2, "Install", "install", 3, 7),
+                (2, "Install", "install-1", 7, 9),
+                (1, "Usage & Tips", "usage--tips", 9, 11),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn forked_skill_instructions_reach_the_sub_context() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = TempDir::new();
+        let contexts = tmp.0.join("contexts");
+        // Stands in for chibi: answers with the sub-context's system prompt and the prompt it got
+        let stub = tmp.0.join("chibi");
+        let script = format!("#!/bin/sh\ncat \"{}/$2/system_prompt.md\"\necho \"PROMPT: $3\"\n", contexts.display());
+        fs::write(&stub, script).unwrap();
+        fs::set_permissions(&stub, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let skill = Skill {
+            name: "research".to_string(),
+            dir: tmp.0.join("research"),
+            body: "Search three sources before answering.".to_string(),
+            fork_context: true,
+            ..Default::default()
+        };
+        let result = run_in_sub_context(&stub.to_string_lossy(), &contexts, &skill, Some("find the spec")).unwrap();
+
+        assert!(result.contains("Search three sources before answering."), "{}", result);
+        assert!(result.contains("PROMPT: find the spec"), "{}", result);
+        assert!(!result.contains("Carry out"), "{}", result);
+    }
+}
@@ -0,0 +1,201 @@
+//! MCP stdio server (`agent-skills mcp`): the same tools, plus skills as prompts and resources
+
+use super::{enabled_skills, find_skill, read_skill_path, record_usage, skill_files, skill_instructions, tool_table};
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::process::Command;
+
+/// MCP protocol revision implemented by `agent-skills mcp`
+const MCP_PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Serve agent-skills' tools, and skills as prompts and resources, over MCP stdio
+///
+/// Messages are newline-delimited JSON-RPC 2.0. Tool calls re-run this binary
+/// through the chibi plugin protocol, so they behave exactly as under chibi.
+pub fn run_mcp_server() {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<serde_json::Value>(&line) {
+            Ok(request) => handle_mcp_request(&request),
+            Err(e) => Some(mcp_error(serde_json::Value::Null, -32700, &format!("Parse error: {}", e))),
+        };
+        if let Some(response) = response {
+            let _ = writeln!(stdout, "{}", response);
+            let _ = stdout.flush();
+        }
+    }
+}
+
+/// Answer one JSON-RPC message; notifications (no id) get no response
+fn handle_mcp_request(request: &serde_json::Value) -> Option<serde_json::Value> {
+    let id = request.get("id").cloned()?;
+    let method = request.get("method").and_then(|m| m.as_str()).unwrap_or_default();
+    let params = request.get("params").cloned().unwrap_or_else(|| serde_json::json!({}));
+
+    let result = match method {
+        "initialize" => Ok(serde_json::json!({
+            "protocolVersion": MCP_PROTOCOL_VERSION,
+            "capabilities": {"tools": {}, "prompts": {}, "resources": {}},
+            "serverInfo": {"name": "agent-skills", "version": env!("CARGO_PKG_VERSION")}
+        })),
+        "ping" => Ok(serde_json::json!({})),
+        "tools/list" => Ok(mcp_list_tools()),
+        "tools/call" => mcp_call_tool(&params),
+        "prompts/list" => Ok(mcp_list_prompts()),
+        "prompts/get" => mcp_get_prompt(&params),
+        "resources/list" => Ok(mcp_list_resources()),
+        "resources/read" => mcp_read_resource(&params),
+        _ => Err((-32601, format!("Method not found: {}", method))),
+    };
+
+    Some(match result {
+        Ok(result) => serde_json::json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err((code, message)) => mcp_error(id, code, &message),
+    })
+}
+
+fn mcp_error(id: serde_json::Value, code: i64, message: &str) -> serde_json::Value {
+    serde_json::json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+fn mcp_list_tools() -> serde_json::Value {
+    let tools: Vec<serde_json::Value> = tool_table()
+        .into_iter()
+        .filter(|t| t.enabled)
+        .map(|t| {
+            serde_json::json!({
+                "name": t.name,
+                "description": t.schema["description"],
+                "inputSchema": t.schema["parameters"]
+            })
+        })
+        .collect();
+    serde_json::json!({ "tools": tools })
+}
+
+fn mcp_call_tool(params: &serde_json::Value) -> Result<serde_json::Value, (i64, String)> {
+    let name = params
+        .get("name")
+        .and_then(|n| n.as_str())
+        .ok_or((-32602, "Missing tool name".to_string()))?;
+    let arguments = params.get("arguments").cloned().unwrap_or_else(|| serde_json::json!({}));
+
+    let exe = env::current_exe().map_err(|e| (-32603, e.to_string()))?;
+    let mut child = Command::new(exe)
+        .env("CHIBI_TOOL_NAME", name)
+        .env_remove("CHIBI_HOOK")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| (-32603, format!("Error running tool: {}", e)))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(arguments.to_string().as_bytes());
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| (-32603, format!("Error running tool: {}", e)))?;
+
+    let text = String::from_utf8_lossy(&output.stdout).trim_end().to_string();
+    Ok(serde_json::json!({
+        "content": [{"type": "text", "text": text}],
+        "isError": text.starts_with("Error")
+    }))
+}
+
+fn mcp_list_prompts() -> serde_json::Value {
+    let prompts: Vec<serde_json::Value> = enabled_skills()
+        .iter()
+        .map(|skill| {
+            serde_json::json!({
+                "name": skill.id(),
+                "description": skill.description,
+                "arguments": [{
+                    "name": "arguments",
+                    "description": "Arguments to pass to the skill (optional)",
+                    "required": false
+                }]
+            })
+        })
+        .collect();
+    serde_json::json!({ "prompts": prompts })
+}
+
+fn mcp_get_prompt(params: &serde_json::Value) -> Result<serde_json::Value, (i64, String)> {
+    let name = params
+        .get("name")
+        .and_then(|n| n.as_str())
+        .ok_or((-32602, "Missing prompt name".to_string()))?;
+    let skills = enabled_skills();
+    let skill = find_skill(&skills, name).map_err(|e| (-32602, e))?;
+    record_usage(&skill.id(), |s| s.invocations += 1);
+
+    let mut text = skill_instructions(skill);
+    let arguments = params
+        .pointer("/arguments/arguments")
+        .and_then(|a| a.as_str())
+        .filter(|a| !a.is_empty());
+    if let Some(arguments) = arguments {
+        text.push_str(&format!("\n\n## Arguments\n{}", arguments));
+    }
+
+    Ok(serde_json::json!({
+        "description": skill.description,
+        "messages": [{"role": "user", "content": {"type": "text", "text": text}}]
+    }))
+}
+
+/// Every file of every enabled skill, as `skill://<id>/<path>` resources
+fn mcp_list_resources() -> serde_json::Value {
+    let mut resources = Vec::new();
+    for skill in enabled_skills() {
+        for rel in skill_files(&skill.dir, Path::new("")) {
+            let rel = rel.to_string_lossy().to_string();
+            resources.push(serde_json::json!({
+                "uri": format!("skill://{}/{}", skill.id(), rel),
+                "name": format!("{}/{}", skill.id(), rel),
+                "mimeType": mime_type(&rel)
+            }));
+        }
+    }
+    serde_json::json!({ "resources": resources })
+}
+
+fn mcp_read_resource(params: &serde_json::Value) -> Result<serde_json::Value, (i64, String)> {
+    let uri = params
+        .get("uri")
+        .and_then(|u| u.as_str())
+        .ok_or((-32602, "Missing resource uri".to_string()))?;
+    let not_found = || (-32002, format!("Resource not found: {}", uri));
+    let rest = uri.strip_prefix("skill://").ok_or_else(not_found)?;
+
+    // Skill ids contain `/` for namespaced skills, so match the longest id prefix
+    let mut skills = enabled_skills();
+    skills.sort_by_key(|s| std::cmp::Reverse(s.id().len()));
+    let (skill, rel) = skills
+        .iter()
+        .find_map(|s| rest.strip_prefix(&format!("{}/", s.id())).map(|rel| (s, rel)))
+        .ok_or_else(not_found)?;
+
+    let text = read_skill_path(&skill.dir, rel).map_err(|e| (-32002, e))?;
+    Ok(serde_json::json!({
+        "contents": [{"uri": uri, "mimeType": mime_type(rel), "text": text}]
+    }))
+}
+
+fn mime_type(path: &str) -> &'static str {
+    match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some("md") => "text/markdown",
+        Some("json") => "application/json",
+        Some("py") => "text/x-python",
+        Some("sh") => "text/x-shellscript",
+        _ => "text/plain",
+    }
+}
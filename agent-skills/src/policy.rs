@@ -0,0 +1,254 @@
+//! Admin policy (`policy.json`): allowed skill sources, blocked skills and script size limits
+
+use super::{load_install_record, policy_file, Skill, SkillSource};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Admin policy loaded from policy.json in the plugin directory
+///
+/// Empty source lists mean any source is allowed; otherwise a source must
+/// match at least one owner, repo or URL pattern.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Policy {
+    /// GitHub owners skills may be installed from
+    pub allowed_owners: Vec<String>,
+    /// GitHub repositories skills may be installed from, as `owner/repo`
+    pub allowed_repos: Vec<String>,
+    /// Glob patterns for repository URLs, e.g. `https://git.example.com/*`
+    pub allowed_urls: Vec<String>,
+    /// Only install skills whose source commit has a valid signature
+    pub require_signature: bool,
+    /// Skill names or ids that may never be installed or used
+    pub blocked_skills: Vec<String>,
+    /// Largest script, in bytes, a skill may ship or run
+    pub max_script_bytes: Option<u64>,
+}
+
+/// Load the admin policy; a policy file that can't be parsed fails closed
+pub fn load_policy() -> Result<Policy, String> {
+    let path = policy_file();
+    if !path.exists() {
+        return Ok(Policy::default());
+    }
+    let content = fs::read_to_string(&path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let policy: Policy =
+        serde_json::from_str(&content).map_err(|e| format!("Invalid policy file {}: {}", path.display(), e))?;
+    for pattern in &policy.allowed_urls {
+        glob::Pattern::new(pattern)
+            .map_err(|e| format!("Invalid URL pattern '{}' in {}: {}", pattern, path.display(), e))?;
+    }
+    Ok(policy)
+}
+
+pub fn is_blocked_skill(policy: &Policy, name: &str, id: &str) -> bool {
+    policy.blocked_skills.iter().any(|b| b == name || b == id)
+}
+
+fn has_source_allowlist(policy: &Policy) -> bool {
+    !(policy.allowed_owners.is_empty() && policy.allowed_repos.is_empty() && policy.allowed_urls.is_empty())
+}
+
+/// Check an install source against the policy before anything is fetched
+pub fn check_source_policy(policy: &Policy, source: &SkillSource) -> Result<(), String> {
+    if is_blocked_skill(policy, &source.name, &source.id()) {
+        return Err(format!("Skill '{}' is blocked by policy.", source.id()));
+    }
+    if !has_source_allowlist(policy) || source_url_allowed(policy, &source.repo_url) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Source '{}' for skill '{}' is not allowed by policy.",
+            source.repo_url,
+            source.id()
+        ))
+    }
+}
+
+/// Whether a repository URL passes the policy's source allowlist
+///
+/// Owners and repos only ever name GitHub repositories
+/// (`https://github.com/<owner>/<repo>`); other hosts and `file://` sources
+/// must match an `allowed_urls` pattern.
+fn source_url_allowed(policy: &Policy, repo_url: &str) -> bool {
+    if let Some(path) = repo_url.strip_prefix("https://github.com/") {
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        // `..` would let the request path leave the owner it names
+        if !segments.iter().any(|s| *s == "." || *s == "..") {
+            let owner_ok = segments
+                .first()
+                .is_some_and(|o| policy.allowed_owners.iter().any(|a| a.eq_ignore_ascii_case(o)));
+            let repo_ok = segments.get(0..2).is_some_and(|s| {
+                let repo = s.join("/");
+                let repo = repo.trim_end_matches(".git");
+                policy.allowed_repos.iter().any(|a| a.eq_ignore_ascii_case(repo))
+            });
+            if owner_ok || repo_ok {
+                return true;
+            }
+        }
+    }
+    policy
+        .allowed_urls
+        .iter()
+        .filter_map(|p| glob::Pattern::new(p).ok())
+        .any(|p| p.matches(repo_url))
+}
+
+/// Why an installed skill is not allowed by the policy, if it isn't
+///
+/// Top-level skills were placed by hand rather than installed, so only the
+/// name and script checks apply to them. Installed skills are checked against
+/// the source URL in their install record; without one their source is unknown.
+pub fn policy_violation(policy: &Policy, skill: &Skill) -> Option<String> {
+    if is_blocked_skill(policy, &skill.name, &skill.id()) {
+        return Some("the skill is blocked".to_string());
+    }
+    if skill.namespace.is_some() && has_source_allowlist(policy) {
+        match load_install_record(&skill.dir) {
+            None => return Some("its source is unknown (no install record)".to_string()),
+            Some(record) if !source_url_allowed(policy, &record.url) => {
+                return Some(format!("source '{}' is not allowed", record.url));
+            }
+            Some(_) => {}
+        }
+    }
+    oversized_script(policy, &skill.dir)
+}
+
+/// Find a script under `scripts/` that exceeds the policy's size limit
+pub fn oversized_script(policy: &Policy, skill_dir: &Path) -> Option<String> {
+    let max = policy.max_script_bytes?;
+    let mut pending = vec![skill_dir.join("scripts")];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            let Ok(meta) = entry.metadata() else { continue };
+            if meta.is_dir() {
+                pending.push(path);
+            } else if meta.len() > max {
+                let rel = path.strip_prefix(skill_dir).unwrap_or(&path);
+                return Some(format!(
+                    "script '{}' is {} bytes, over the {} byte limit",
+                    rel.display(),
+                    meta.len(),
+                    max
+                ));
+            }
+        }
+    }
+    None
+}
+
+/// Verify the signature on the checked-out commit of a cloned source
+pub fn verify_source_signature(repo_dir: &Path) -> Result<(), String> {
+    let output = Command::new("git")
+        .args(["-C", &repo_dir.to_string_lossy(), "verify-commit", "HEAD"])
+        .output()
+        .map_err(|e| format!("Error running git: {}", e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Policy requires signed sources, and the commit signature could not be verified: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TempDir;
+
+    fn policy(json: serde_json::Value) -> Policy {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn owners_and_repos_only_cover_github() {
+        let policy = policy(serde_json::json!({
+            "allowed_owners": ["Acme"],
+            "allowed_repos": ["other/skills"],
+            "allowed_urls": ["https://git.example.com/*"]
+        }));
+        assert!(source_url_allowed(&policy, "https://github.com/acme/skills"));
+        assert!(source_url_allowed(&policy, "https://github.com/other/skills.git"));
+        assert!(source_url_allowed(&policy, "https://git.example.com/team/skills"));
+
+        assert!(!source_url_allowed(&policy, "https://github.com/other/tools"));
+        assert!(!source_url_allowed(&policy, "https://github.com/acme/../evil/skills"));
+        assert!(!source_url_allowed(&policy, "https://gitlab.com/acme/skills"));
+        assert!(!source_url_allowed(&policy, "file:///srv/acme/skills"));
+    }
+
+    fn skill_in(dir: &Path, namespace: Option<&str>, name: &str) -> Skill {
+        let dir = dir.join(name);
+        fs::create_dir_all(dir.join("scripts")).unwrap();
+        Skill {
+            namespace: namespace.map(String::from),
+            dir,
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn write_record(skill: &Skill, url: &str) {
+        let record = serde_json::json!({
+            "source": url,
+            "url": url,
+            "commit": null,
+            "installed_at": 0,
+            "installer_version": "0.1.0",
+            "files": {}
+        });
+        fs::write(skill.dir.join(".install.json"), record.to_string()).unwrap();
+    }
+
+    #[test]
+    fn installed_skills_are_checked_against_their_recorded_source() {
+        let tmp = TempDir::new();
+        let policy = policy(serde_json::json!({ "allowed_owners": ["acme"], "blocked_skills": ["other/bad"] }));
+
+        let trusted = skill_in(&tmp.0, Some("acme"), "pdf");
+        write_record(&trusted, "https://github.com/acme/skills");
+        assert_eq!(policy_violation(&policy, &trusted), None);
+
+        let untrusted = skill_in(&tmp.0, Some("other"), "notes");
+        write_record(&untrusted, "https://github.com/other/skills");
+        assert_eq!(
+            policy_violation(&policy, &untrusted).as_deref(),
+            Some("source 'https://github.com/other/skills' is not allowed")
+        );
+
+        let unrecorded = skill_in(&tmp.0, Some("acme"), "draw");
+        assert_eq!(
+            policy_violation(&policy, &unrecorded).as_deref(),
+            Some("its source is unknown (no install record)")
+        );
+
+        // Hand-placed top-level skills have no source to check
+        assert_eq!(policy_violation(&policy, &skill_in(&tmp.0, None, "local")), None);
+
+        let blocked = skill_in(&tmp.0, Some("other"), "bad");
+        assert_eq!(policy_violation(&policy, &blocked).as_deref(), Some("the skill is blocked"));
+    }
+
+    #[test]
+    fn oversized_scripts_violate_the_policy() {
+        let tmp = TempDir::new();
+        let policy = policy(serde_json::json!({ "max_script_bytes": 10 }));
+        let skill = skill_in(&tmp.0, None, "big");
+        fs::create_dir_all(skill.dir.join("scripts/nested")).unwrap();
+        fs::write(skill.dir.join("scripts/small.sh"), "echo hi").unwrap();
+        assert_eq!(policy_violation(&policy, &skill), None);
+
+        fs::write(skill.dir.join("scripts/nested/big.sh"), "echo hello world").unwrap();
+        assert_eq!(
+            policy_violation(&policy, &skill).as_deref(),
+            Some("script 'scripts/nested/big.sh' is 16 bytes, over the 10 byte limit")
+        );
+    }
+}
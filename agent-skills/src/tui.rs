@@ -0,0 +1,355 @@
+//! Interactive terminal browser for installed and cached skills (`agent-skills tui`)
+//!
+//! Mutating actions run this binary's own CLI as a subprocess so they go through
+//! the same policy, dependency and trust checks as the command line.
+
+use super::{
+    available_skills, discover_skills, is_skill_enabled, parse_skill_ref, read_skill_path, skill_files, AvailableSkill, Skill,
+};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const HELP: &str =
+    "j/k move  enter open  f files  e toggle  i install  d remove  t test  r refresh  esc back  q quit";
+
+enum Entry {
+    Installed { skill: Box<Skill>, enabled: bool },
+    Available(AvailableSkill),
+}
+
+impl Entry {
+    fn label(&self) -> String {
+        match self {
+            Entry::Installed { skill, enabled } => {
+                format!("[{}] {}", if *enabled { "x" } else { " " }, skill.id())
+            }
+            Entry::Available(a) => format!(" +  {}", a.name),
+        }
+    }
+}
+
+/// What the right-hand pane shows
+enum View {
+    Skill,
+    Files { files: Vec<PathBuf>, state: ListState },
+    File { path: String, content: String },
+    Output { title: String, content: String },
+}
+
+struct App {
+    entries: Vec<Entry>,
+    list: ListState,
+    view: View,
+    scroll: u16,
+    status: String,
+    /// Entry index awaiting `y` to confirm removal
+    confirm_remove: Option<usize>,
+    /// No source is cached, so nothing shows up as available to install
+    no_sources: bool,
+}
+
+/// Run the browser until the user quits
+pub fn run() -> Result<(), String> {
+    let mut terminal = ratatui::init();
+    let mut app = App::new();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    result.map_err(|e| e.to_string())
+}
+
+impl App {
+    fn new() -> Self {
+        let mut app = App {
+            entries: Vec::new(),
+            list: ListState::default(),
+            view: View::Skill,
+            scroll: 0,
+            status: HELP.to_string(),
+            confirm_remove: None,
+            no_sources: false,
+        };
+        app.refresh();
+        app
+    }
+
+    /// Reload installed skills, then cached skills that are not installed yet
+    fn refresh(&mut self) {
+        let mut installed = discover_skills();
+        installed.sort_by_key(|s| s.id());
+        let installed_ids: Vec<String> = installed.iter().map(|s| s.id()).collect();
+
+        let mut entries: Vec<Entry> = installed
+            .into_iter()
+            .map(|skill| {
+                let enabled = is_skill_enabled(&skill.id());
+                Entry::Installed { skill: Box::new(skill), enabled }
+            })
+            .collect();
+        let available = available_skills();
+        self.no_sources = available.is_empty();
+        entries.extend(
+            available
+                .into_iter()
+                .filter(|a| parse_skill_ref(&a.install_ref()).ok().is_none_or(|s| !installed_ids.contains(&s.id())))
+                .map(Entry::Available),
+        );
+        self.entries = entries;
+
+        let selected = self.list.selected().unwrap_or(0);
+        self.list
+            .select((!self.entries.is_empty()).then(|| selected.min(self.entries.len() - 1)));
+    }
+
+    fn selected(&self) -> Option<&Entry> {
+        self.list.selected().and_then(|i| self.entries.get(i))
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            if let Some(index) = self.confirm_remove.take() {
+                if key.code == KeyCode::Char('y') {
+                    self.remove(index);
+                } else {
+                    self.status = "Removal cancelled.".to_string();
+                }
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('q') => return Ok(()),
+                KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+                KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+                KeyCode::PageDown => self.scroll = self.scroll.saturating_add(10),
+                KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+                KeyCode::Enter => self.open(),
+                KeyCode::Esc => self.back(),
+                KeyCode::Char('f') => self.show_files(),
+                KeyCode::Char('e') => self.toggle(),
+                KeyCode::Char('i') => self.install(),
+                KeyCode::Char('d') => self.ask_remove(),
+                KeyCode::Char('t') => self.test(terminal)?,
+                KeyCode::Char('r') => {
+                    self.refresh();
+                    self.status = "Refreshed.".to_string();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn move_selection(&mut self, delta: i32) {
+        if let View::Files { files, state } = &mut self.view {
+            if !files.is_empty() {
+                let next = state.selected().unwrap_or(0) as i32 + delta;
+                state.select(Some(next.clamp(0, files.len() as i32 - 1) as usize));
+            }
+            return;
+        }
+        if self.entries.is_empty() {
+            return;
+        }
+        let next = self.list.selected().unwrap_or(0) as i32 + delta;
+        self.list
+            .select(Some(next.clamp(0, self.entries.len() as i32 - 1) as usize));
+        self.view = View::Skill;
+        self.scroll = 0;
+    }
+
+    fn open(&mut self) {
+        let View::Files { files, state } = &self.view else {
+            self.view = View::Skill;
+            return;
+        };
+        let (Some(path), Some(Entry::Installed { skill, .. })) =
+            (state.selected().and_then(|i| files.get(i)), self.selected())
+        else {
+            return;
+        };
+        let path = path.to_string_lossy().to_string();
+        let content = read_skill_path(&skill.dir, &path).unwrap_or_else(|e| format!("Error: {}", e));
+        self.view = View::File { path, content };
+        self.scroll = 0;
+    }
+
+    fn back(&mut self) {
+        self.view = match std::mem::replace(&mut self.view, View::Skill) {
+            View::File { .. } => {
+                self.show_files();
+                return;
+            }
+            _ => View::Skill,
+        };
+        self.scroll = 0;
+    }
+
+    fn show_files(&mut self) {
+        let Some(Entry::Installed { skill, .. }) = self.selected() else {
+            self.status = "Files are only available for installed skills.".to_string();
+            return;
+        };
+        let files = skill_files(&skill.dir, Path::new(""));
+        let mut state = ListState::default();
+        state.select((!files.is_empty()).then_some(0));
+        self.view = View::Files { files, state };
+        self.scroll = 0;
+    }
+
+    fn toggle(&mut self) {
+        let Some(Entry::Installed { skill, enabled }) = self.selected() else {
+            return;
+        };
+        let action = if *enabled { "disable" } else { "enable" };
+        let id = skill.id();
+        self.run_cli(&[action, &id]);
+    }
+
+    fn install(&mut self) {
+        let Some(Entry::Available(available)) = self.selected() else {
+            self.status = "Select a cached skill (marked +) to install.".to_string();
+            return;
+        };
+        let install_ref = available.install_ref();
+        self.run_cli(&["install", &install_ref]);
+    }
+
+    fn ask_remove(&mut self) {
+        let Some(Entry::Installed { skill, .. }) = self.selected() else {
+            return;
+        };
+        self.status = format!("Remove '{}'? (y/n)", skill.id());
+        self.confirm_remove = self.list.selected();
+    }
+
+    fn remove(&mut self, index: usize) {
+        let Some(Entry::Installed { skill, .. }) = self.entries.get(index) else {
+            return;
+        };
+        let id = skill.id();
+        self.run_cli(&["remove", &id]);
+    }
+
+    /// Tests may ask for script approval on the terminal, so they run with the UI suspended
+    fn test(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        let Some(Entry::Installed { skill, .. }) = self.selected() else {
+            return Ok(());
+        };
+        let id = skill.id();
+        ratatui::restore();
+        let output = cli_output(&["test", &id]);
+        *terminal = ratatui::init();
+        terminal.clear()?;
+        self.show_output(format!("test {}", id), output);
+        Ok(())
+    }
+
+    /// Run one of our own CLI actions and show what it printed
+    fn run_cli(&mut self, args: &[&str]) {
+        let output = cli_output(args);
+        let title = args.join(" ");
+        self.refresh();
+        self.show_output(title, output);
+    }
+
+    fn show_output(&mut self, title: String, content: String) {
+        self.status = content.lines().last().unwrap_or("").to_string();
+        self.view = View::Output { title, content };
+        self.scroll = 0;
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(1),
+                Constraint::Length(u16::from(self.no_sources)),
+                Constraint::Length(1),
+            ])
+            .split(frame.area());
+        if self.no_sources {
+            let banner = "No cached sources: run `agent-skills mirror <owner>` to browse skills to install.";
+            let banner = Paragraph::new(Line::from(banner)).style(Style::default().add_modifier(Modifier::BOLD));
+            frame.render_widget(banner, rows[1]);
+        }
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
+            .split(rows[0]);
+
+        let items: Vec<ListItem> = self.entries.iter().map(|e| ListItem::new(e.label())).collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("Skills"))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, columns[0], &mut self.list);
+
+        let (title, content) = match &mut self.view {
+            View::Files { files, state } => {
+                let items: Vec<ListItem> = files
+                    .iter()
+                    .map(|f| ListItem::new(f.to_string_lossy().to_string()))
+                    .collect();
+                let list = List::new(items)
+                    .block(Block::default().borders(Borders::ALL).title("Files"))
+                    .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+                frame.render_stateful_widget(list, columns[1], state);
+                frame.render_widget(Paragraph::new(Line::from(self.status.as_str())), rows[2]);
+                return;
+            }
+            View::File { path, content } => (path.clone(), content.clone()),
+            View::Output { title, content } => (title.clone(), content.clone()),
+            View::Skill => match self.entries.get(self.list.selected().unwrap_or(usize::MAX)) {
+                Some(Entry::Installed { skill, .. }) => (
+                    skill.id(),
+                    fs::read_to_string(skill.dir.join("SKILL.md")).unwrap_or_default(),
+                ),
+                Some(Entry::Available(a)) => (
+                    format!("{} (not installed)", a.install_ref()),
+                    format!("{}\n\n{}", a.description, a.content),
+                ),
+                None => (
+                    "No skills".to_string(),
+                    "Install skills or cache sources with `agent-skills mirror <owner>`.".to_string(),
+                ),
+            },
+        };
+
+        let pane = Paragraph::new(content)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0));
+        frame.render_widget(pane, columns[1]);
+        frame.render_widget(Paragraph::new(Line::from(self.status.as_str())), rows[2]);
+    }
+}
+
+/// Output of `agent-skills <args>`, stdout and stderr combined
+fn cli_output(args: &[&str]) -> String {
+    let exe = match env::current_exe() {
+        Ok(e) => e,
+        Err(e) => return format!("Error: {}", e),
+    };
+    match Command::new(exe).args(args).output() {
+        Ok(output) => format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ),
+        Err(e) => format!("Error: {}", e),
+    }
+}
@@ -0,0 +1,259 @@
+//! Install from a local `file://` repository through the CLI
+//!
+//! The plugin keeps its state next to the executable, so each test copies the
+//! binary into a scratch plugin directory of its own. Needs `git` on PATH.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// A scratch plugin directory and a source repository under `<root>/trusted-org/repo`
+struct Fixture {
+    root: PathBuf,
+}
+
+impl Fixture {
+    fn new(skills: &[&str]) -> Self {
+        let root = std::env::temp_dir().join(format!(
+            "agent-skills-test-{}-{}",
+            std::process::id(),
+            NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&root);
+        let fixture = Fixture { root };
+        fs::create_dir_all(fixture.plugin_dir()).unwrap();
+        fs::copy(env!("CARGO_BIN_EXE_agent-skills"), fixture.plugin_dir().join("agent-skills")).unwrap();
+
+        let repo = fixture.repo_dir();
+        for name in skills {
+            let dir = repo.join("skills").join(name);
+            fs::create_dir_all(&dir).unwrap();
+            let skill_md = format!("---\nname: {}\ndescription: Test skill {}\n---\nBody\n", name, name);
+            fs::write(dir.join("SKILL.md"), skill_md).unwrap();
+        }
+        git(&repo, &["init", "-q"]);
+        git(&repo, &["add", "-A"]);
+        git(&repo, &["-c", "user.name=test", "-c", "user.email=test@example.com", "commit", "-q", "-m", "init"]);
+        fixture
+    }
+
+    fn plugin_dir(&self) -> PathBuf {
+        self.root.join("plugin")
+    }
+
+    fn repo_dir(&self) -> PathBuf {
+        self.root.join("trusted-org").join("repo")
+    }
+
+    fn repo_url(&self) -> String {
+        format!("file://{}", self.repo_dir().display())
+    }
+
+    fn skill_ref(&self, name: &str) -> String {
+        format!("{}#{}", self.repo_url(), name)
+    }
+
+    fn installed_dir(&self, name: &str) -> PathBuf {
+        self.plugin_dir().join("skills").join("trusted-org").join(name)
+    }
+
+    /// Run the plugin without `--json`, returning the exit code and stdout
+    fn run_text(&self, args: &[&str]) -> (i32, String) {
+        let output = Command::new(self.plugin_dir().join("agent-skills"))
+            .args(args)
+            .current_dir(&self.root)
+            .output()
+            .unwrap();
+        (output.status.code().unwrap(), String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Run the plugin with `--json`, returning the exit code and the result envelope
+    fn run(&self, args: &[&str]) -> (i32, serde_json::Value) {
+        let output = Command::new(self.plugin_dir().join("agent-skills"))
+            .args(args)
+            .arg("--json")
+            .current_dir(&self.root)
+            .output()
+            .unwrap();
+        let envelope = serde_json::from_slice(&output.stdout)
+            .unwrap_or_else(|e| panic!("bad envelope ({}): {}", e, String::from_utf8_lossy(&output.stdout)));
+        (output.status.code().unwrap(), envelope)
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git").args(args).current_dir(dir).status().unwrap();
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+#[test]
+fn failed_install_rolls_back_every_skill() {
+    let fixture = Fixture::new(&["alpha"]);
+    let (code, envelope) = fixture.run(&["install", &fixture.skill_ref("alpha"), &fixture.skill_ref("missing")]);
+
+    assert_eq!(code, 3);
+    assert_eq!(envelope["error"]["code"], "not_found");
+    assert!(!fixture.installed_dir("alpha").exists());
+    assert!(!fixture.plugin_dir().join("skills").join(".install_txn").exists());
+}
+
+#[test]
+fn policy_owners_do_not_cover_file_sources() {
+    let fixture = Fixture::new(&["alpha"]);
+    let policy = fixture.plugin_dir().join("policy.json");
+
+    fs::write(&policy, r#"{"allowed_owners": ["trusted-org"]}"#).unwrap();
+    let (code, envelope) = fixture.run(&["install", &fixture.skill_ref("alpha")]);
+    assert_eq!(code, 5);
+    assert_eq!(envelope["error"]["code"], "policy");
+    assert!(!fixture.installed_dir("alpha").exists());
+
+    let allowed = serde_json::json!({ "allowed_urls": [format!("{}*", fixture.repo_url())] });
+    fs::write(&policy, allowed.to_string()).unwrap();
+    let (code, envelope) = fixture.run(&["install", &fixture.skill_ref("alpha")]);
+    assert_eq!(code, 0, "{}", envelope);
+    assert!(fixture.installed_dir("alpha").join("SKILL.md").exists());
+}
+
+#[test]
+fn install_record_tracks_provenance_and_changes() {
+    let fixture = Fixture::new(&["alpha"]);
+    let (code, envelope) = fixture.run(&["install", &fixture.skill_ref("alpha")]);
+    assert_eq!(code, 0, "{}", envelope);
+    assert_eq!(envelope["data"]["installed"], serde_json::json!(["trusted-org/alpha"]));
+
+    let record: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(fixture.installed_dir("alpha").join(".install.json")).unwrap())
+            .unwrap();
+    assert_eq!(record["url"], fixture.repo_url());
+    assert_eq!(record["source"], fixture.skill_ref("alpha"));
+    assert_eq!(record["commit"].as_str().map(str::len), Some(40));
+    let files: Vec<&String> = record["files"].as_object().unwrap().keys().collect();
+    assert_eq!(files, ["SKILL.md"]);
+
+    let (_, listing) = fixture.run(&["list_installed"]);
+    assert_eq!(listing["data"][0]["modified"], false);
+
+    // Hidden files count as changes too
+    fs::write(fixture.installed_dir("alpha").join(".env"), "TOKEN=1\n").unwrap();
+    let (_, listing) = fixture.run(&["list_installed"]);
+    assert_eq!(listing["data"][0]["modified"], true);
+}
+
+#[test]
+fn offline_install_needs_a_mirrored_source() {
+    let fixture = Fixture::new(&["alpha"]);
+    let (code, envelope) = fixture.run(&["install", &fixture.skill_ref("alpha"), "--offline"]);
+    assert_eq!(code, 6);
+    assert_eq!(envelope["error"]["code"], "source");
+    assert!(!fixture.installed_dir("alpha").exists());
+
+    let (code, envelope) = fixture.run(&["mirror", &fixture.repo_url()]);
+    assert_eq!(code, 0, "{}", envelope);
+    assert_eq!(envelope["data"]["mirrored"], serde_json::json!([fixture.repo_url()]));
+
+    // The source is gone; the mirrored copy is enough
+    fs::remove_dir_all(fixture.repo_dir()).unwrap();
+    let (code, envelope) = fixture.run(&["install", &fixture.skill_ref("alpha"), "--offline"]);
+    assert_eq!(code, 0, "{}", envelope);
+    assert!(fixture.installed_dir("alpha").join("SKILL.md").exists());
+}
+
+#[test]
+fn online_install_falls_back_to_the_cache_when_the_source_is_unreachable() {
+    let fixture = Fixture::new(&["alpha", "beta"]);
+    let (code, envelope) = fixture.run(&["install", &fixture.skill_ref("alpha")]);
+    assert_eq!(code, 0, "{}", envelope);
+
+    fs::remove_dir_all(fixture.repo_dir()).unwrap();
+    let (code, envelope) = fixture.run(&["install", &fixture.skill_ref("beta")]);
+    assert_eq!(code, 0, "{}", envelope);
+    assert!(fixture.installed_dir("beta").join("SKILL.md").exists());
+
+    let (code, envelope) = fixture.run(&["mirror", &fixture.repo_url()]);
+    assert_eq!(code, 0, "{}", envelope);
+    let (code, envelope) = fixture.run(&["mirror", "file:///nonexistent/agent-skills-test/repo"]);
+    assert_eq!(code, 6, "{}", envelope);
+    assert_eq!(envelope["error"]["code"], "source");
+}
+
+#[test]
+fn remove_is_refused_while_an_install_holds_the_lock() {
+    use fs2::FileExt;
+
+    let fixture = Fixture::new(&["alpha"]);
+    let (code, envelope) = fixture.run(&["install", &fixture.skill_ref("alpha")]);
+    assert_eq!(code, 0, "{}", envelope);
+
+    let lock = fs::File::create(fixture.plugin_dir().join(".install.lock")).unwrap();
+    lock.lock_exclusive().unwrap();
+    let (code, envelope) = fixture.run(&["remove", "trusted-org/alpha"]);
+    assert_eq!(code, 4, "{}", envelope);
+    assert_eq!(envelope["error"]["code"], "conflict");
+    assert!(fixture.installed_dir("alpha").exists());
+
+    drop(lock);
+    let (code, envelope) = fixture.run(&["remove", "trusted-org/alpha"]);
+    assert_eq!(code, 0, "{}", envelope);
+    assert!(!fixture.installed_dir("alpha").exists());
+}
+
+#[test]
+fn stats_ages_unused_skills_from_the_install_record() {
+    let fixture = Fixture::new(&["alpha"]);
+    let (code, envelope) = fixture.run(&["install", &fixture.skill_ref("alpha")]);
+    assert_eq!(code, 0, "{}", envelope);
+
+    let (_, output) = fixture.run_text(&["stats", "--unused"]);
+    assert!(output.contains("No unused skills."), "{}", output);
+
+    // SKILL.md is fresh, but the record says it was installed long ago
+    let path = fixture.installed_dir("alpha").join(".install.json");
+    let mut record: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    record["installed_at"] = serde_json::json!(0);
+    fs::write(&path, record.to_string()).unwrap();
+    let (code, output) = fixture.run_text(&["stats", "--unused"]);
+    assert_eq!(code, 0, "{}", output);
+    assert!(output.contains("trusted-org/alpha"), "{}", output);
+}
+
+#[test]
+fn cli_actions_report_through_the_json_envelope() {
+    let fixture = Fixture::new(&["alpha"]);
+    let (code, envelope) = fixture.run(&["install", &fixture.skill_ref("alpha")]);
+    assert_eq!(code, 0, "{}", envelope);
+
+    let (code, envelope) = fixture.run(&["stats"]);
+    assert_eq!(code, 0, "{}", envelope);
+    assert_eq!(envelope["data"][0]["id"], "trusted-org/alpha");
+    assert_eq!(envelope["data"][0]["last_used"], serde_json::Value::Null);
+
+    let (code, envelope) = fixture.run(&["audit", "--skill", "trusted-org/alpha"]);
+    assert_eq!(code, 0, "{}", envelope);
+    assert!(envelope["data"].is_array(), "{}", envelope);
+
+    let (code, envelope) = fixture.run(&["test", "trusted-org/alpha"]);
+    assert_eq!(code, 0, "{}", envelope);
+    assert_eq!(envelope["data"]["passed"], serde_json::json!([]));
+
+    let (code, envelope) = fixture.run(&["trust", "trusted-org/alpha", "scripts/missing.sh"]);
+    assert_eq!(code, 3, "{}", envelope);
+    assert_eq!(envelope["error"]["code"], "not_found");
+
+    let (code, envelope) = fixture.run(&["doctor"]);
+    assert_eq!(envelope["action"], "doctor");
+    assert_eq!(code == 0, envelope["ok"] == true, "{}", envelope);
+
+    let (code, envelope) = fixture.run(&["tui"]);
+    assert_eq!(code, 2);
+    assert_eq!(envelope["error"]["code"], "usage");
+}